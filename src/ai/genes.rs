@@ -1,20 +1,20 @@
 use super::Gene;
-use crate::game::{StandardGame, HEIGHT, WIDTH};
+use crate::game::Game;
 
 pub struct Holes;
 pub struct MaxHeight;
 pub struct Bumpiness;
 pub struct TotalHeight;
 pub struct LinesCleared;
+pub struct Combo;
+pub struct BackToBack;
 
 impl Gene for Holes {
-    fn evaluate(&self, state: &StandardGame) -> f64 {
-        let mut state = state.clone();
-        state.tick();
+    fn evaluate(&self, state: &Game) -> f64 {
         let mut holes = 0;
-        for x in 0..WIDTH {
+        for x in 0..state.width() {
             let mut found = false;
-            for y in 0..HEIGHT {
+            for y in 0..state.rows() {
                 if state.grid[y][x].is_none() {
                     found = true;
                 } else if found {
@@ -27,14 +27,12 @@ impl Gene for Holes {
 }
 
 impl Gene for MaxHeight {
-    fn evaluate(&self, state: &StandardGame) -> f64 {
-        let mut state = state.clone();
-        state.tick();
+    fn evaluate(&self, state: &Game) -> f64 {
         let mut max_height = 0;
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
+        for x in 0..state.width() {
+            for y in 0..state.rows() {
                 if state.grid[y][x].is_some() {
-                    max_height = max_height.max(HEIGHT - y);
+                    max_height = max_height.max(state.rows() - y);
                     break;
                 }
             }
@@ -44,16 +42,14 @@ impl Gene for MaxHeight {
 }
 
 impl Gene for Bumpiness {
-    fn evaluate(&self, state: &StandardGame) -> f64 {
-        let mut state = state.clone();
-        state.tick();
+    fn evaluate(&self, state: &Game) -> f64 {
         let mut bumpiness = 0;
         let mut prev_height = 0;
-        for x in 0..WIDTH {
+        for x in 0..state.width() {
             let mut height = 0;
-            for y in 0..HEIGHT {
+            for y in 0..state.rows() {
                 if state.grid[y][x].is_some() {
-                    height = (HEIGHT - y) as i32;
+                    height = (state.rows() - y) as i32;
                     break;
                 }
             }
@@ -67,14 +63,12 @@ impl Gene for Bumpiness {
 }
 
 impl Gene for TotalHeight {
-    fn evaluate(&self, state: &StandardGame) -> f64 {
-        let mut state = state.clone();
-        state.tick();
+    fn evaluate(&self, state: &Game) -> f64 {
         let mut total_height = 0;
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
+        for x in 0..state.width() {
+            for y in 0..state.rows() {
                 if state.grid[y][x].is_some() {
-                    total_height += HEIGHT - y;
+                    total_height += state.rows() - y;
                     break;
                 }
             }
@@ -84,11 +78,23 @@ impl Gene for TotalHeight {
 }
 
 impl Gene for LinesCleared {
-    fn evaluate(&self, state: &StandardGame) -> f64 {
-        let mut state = state.clone();
-        let prev_score = state.score;
-        state.tick();
-        (state.score - prev_score) as f64
+    fn evaluate(&self, state: &Game) -> f64 {
+        state.clear.rows as f64
+    }
+}
+
+impl Gene for Combo {
+    fn evaluate(&self, state: &Game) -> f64 {
+        state.score.combo.map_or(0., |combo| combo as f64 + 1.)
+    }
+}
+
+impl Gene for BackToBack {
+    fn evaluate(&self, state: &Game) -> f64 {
+        state
+            .score
+            .back_to_back
+            .map_or(0., |chain| chain as f64 + 1.)
     }
 }
 
@@ -1,18 +1,23 @@
-use crate::game::{Game, StandardGame, WIDTH};
-use crate::tetromino::Direction;
+use crate::game::{Config, Game};
+use crate::modes::Mode;
+use crate::tetromino::{Direction, Rotation};
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
 use rayon::prelude::*;
+use std::time::Duration;
 
 pub mod genes;
 
 const SCORE_LIMIT: u32 = 1000;
 const MOVE_LIMIT: u32 = 1000;
+/// Game time each simulated piece takes in modes that run on the clock.
+const PIECE_TIME: Duration = Duration::from_millis(250);
 
 pub trait Gene {
-    fn evaluate(&self, state: &StandardGame) -> f64;
+    fn evaluate(&self, state: &Game) -> f64;
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, Default)]
 pub struct DNA(pub Vec<f64>);
 
@@ -46,32 +51,50 @@ impl DNA {
     }
 }
 
+/// Moves that bring a freshly spawned piece to where it should drop:
+/// optionally hold, shift against the left wall, `shifts` to the right,
+/// then turn once.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub hold: bool,
+    pub shifts: usize,
+    pub rotation: Option<Rotation>,
+}
+
 pub struct Population {
     dna: Vec<DNA>,
     genes: Vec<Box<dyn Gene + Sync>>,
+    /// Rules of the games every simulation plays.
+    config: Config,
     rng: SmallRng,
 }
 
 impl Population {
-    pub fn single(dna: DNA, genes: Vec<Box<dyn Gene + Sync>>) -> Self {
+    pub fn single(dna: DNA, genes: Vec<Box<dyn Gene + Sync>>, config: Config) -> Self {
         assert_eq!(genes.len(), dna.0.len());
         Self {
             dna: vec![dna],
             genes,
+            config,
             rng: SmallRng::from_entropy(),
         }
     }
 
-    pub fn new(size: usize, genes: Vec<Box<dyn Gene + Sync>>, seed: u64) -> Self {
+    pub fn new(size: usize, genes: Vec<Box<dyn Gene + Sync>>, seed: u64, config: Config) -> Self {
         let mut rng = SmallRng::seed_from_u64(seed);
         let mut dna = Vec::with_capacity(size);
         for _ in 0..size {
             dna.push(DNA::new_random(genes.len(), &mut rng));
         }
-        Population { dna, genes, rng }
+        Population {
+            dna,
+            genes,
+            config,
+            rng,
+        }
     }
 
-    pub fn instinct(&self, index: usize, state: &StandardGame) -> f64 {
+    pub fn instinct(&self, index: usize, state: &Game) -> f64 {
         self.genes
             .iter()
             .zip(self.dna[index].0.iter())
@@ -79,33 +102,41 @@ impl Population {
             .sum()
     }
 
-    pub fn best_actions(&self, index: usize, game: &StandardGame) -> (usize, usize) {
+    pub fn best_actions(&self, index: usize, game: &Game) -> Placement {
         let states = game.all_possible_states();
-        let (_, &shifts, &rotations) = states
+        let (_, &placement) = states
             .iter()
-            .map(|(state, shifts, rotations)| (self.instinct(index, state), shifts, rotations))
-            .max_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap())
+            .map(|(state, placement)| (self.instinct(index, state), placement))
+            .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
             .unwrap();
-        (shifts, rotations)
+        placement
     }
 
     pub fn simulate(&self, index: usize, seed: u64) -> u32 {
-        let mut game = StandardGame::new_with_seed(seed);
+        let mut game = Game::with_config(seed, self.config.clone());
         let mut moves = 0;
-        while !game.over {
-            let (shifts, rotations) = self.best_actions(index, &game);
-            for _ in 0..WIDTH {
+        while !game.over() {
+            if game.mode().timed() {
+                game.advance(PIECE_TIME);
+                if game.over() {
+                    break;
+                }
+            }
+            let placement = self.best_actions(index, &game);
+            if placement.hold {
+                game.hold();
+            }
+            for _ in 0..game.width() {
                 game.shift(Direction::Left);
             }
-            for _ in 0..shifts {
+            for _ in 0..placement.shifts {
                 game.shift(Direction::Right);
             }
-            for _ in 0..rotations {
-                game.rotate();
+            if let Some(rotation) = placement.rotation {
+                game.rotate(rotation);
             }
             game.hard_drop();
-            game.tick();
-            if game.score >= SCORE_LIMIT {
+            if game.score.lines >= SCORE_LIMIT {
                 break;
             }
             moves += 1;
@@ -113,8 +144,9 @@ impl Population {
                 break;
             }
         }
-        log::debug!("Score: {}", game.score);
-        game.score
+        let fitness = fitness(&game);
+        log::debug!("Fitness: {fitness}");
+        fitness
     }
 
     pub fn rank_generation(&self, seed: u64) -> Vec<u32> {
@@ -187,30 +219,64 @@ impl Population {
     // TODO: add a lot of logs
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> Game<WIDTH, HEIGHT> {
-    fn all_possible_states(&self) -> Vec<(Self, usize, usize)> {
+/// How well a simulated game went, higher being better: what the mode is
+/// scored by where more is better, and cleared lines otherwise.
+fn fitness(game: &Game) -> u32 {
+    match game.mode() {
+        Mode::Ultra { .. } => game.score.points,
+        // tenths of a second keep the sum of a generation's ranks in a u32
+        Mode::Survival => (game.time.as_millis() / 100) as u32,
+        Mode::Cheese { .. } => game.garbage_cleared(),
+        _ => game.score.lines,
+    }
+}
+
+impl Game {
+    fn all_possible_states(&self) -> Vec<(Self, Placement)> {
         // we assume we have the state where the new tetromino has just spawned
         // we need to check all possible shifts to the right combined with
-        // all possible rotations
+        // all possible rotations, both for this piece and the one in hold
         let mut states = vec![];
-        // always do shifts first, then rotations
-        // first, shift it all the way to the left
-        let mut game = self.clone();
-        for _ in 0..WIDTH {
-            game.shift(Direction::Left);
-        }
-        for shifts in 0..WIDTH {
-            let mut game = game.clone();
-            for _ in 0..shifts {
-                game.shift(Direction::Right);
+        for hold in [false, true] {
+            let mut game = self.clone();
+            if hold {
+                if !game.can_hold {
+                    break;
+                }
+                game.hold();
+                if game.over() {
+                    break;
+                }
+            }
+            // always do shifts first, then rotations
+            // first, shift it all the way to the left
+            for _ in 0..game.width() {
+                game.shift(Direction::Left);
             }
-            for rotations in 0..4 {
+            for shifts in 0..game.width() {
                 let mut game = game.clone();
-                for _ in 0..rotations {
-                    game.rotate();
+                for _ in 0..shifts {
+                    game.shift(Direction::Right);
+                }
+                // every orientation is at most one turn away
+                for rotation in [
+                    None,
+                    Some(Rotation::Clockwise),
+                    Some(Rotation::Half),
+                    Some(Rotation::CounterClockwise),
+                ] {
+                    let mut game = game.clone();
+                    if let Some(rotation) = rotation {
+                        game.rotate(rotation);
+                    }
+                    game.hard_drop();
+                    let placement = Placement {
+                        hold,
+                        shifts,
+                        rotation,
+                    };
+                    states.push((game, placement));
                 }
-                game.hard_drop();
-                states.push((game, shifts, rotations));
             }
         }
         states
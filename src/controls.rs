@@ -1,32 +1,44 @@
-use crate::game::{StandardGame, HEIGHT, WIDTH};
-use crate::tetromino::Direction;
+use crate::game::{Config, Game, BUFFER};
+use crate::modes::STATUS_WIDTH;
+use crate::tetromino::{Direction, Foreground, Rotation, Tetromino};
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 use termion::{
     color::{self, Bg, Fg},
     cursor,
     raw::*,
 };
 
+/// How long a callout like "T-SPIN DOUBLE" stays on screen.
+const CALLOUT_TIME: Duration = Duration::from_secs(2);
+
 pub struct GameController {
-    pub game: StandardGame,
+    pub game: Game,
     pub pause: bool,
+    /// Outline where the active piece is going to land.
+    pub ghost: bool,
+    last_tick: Instant,
     out: RawTerminal<io::Stdout>,
 }
 
 pub enum Action {
-    Turn,
+    Turn(Rotation),
     Shift(Direction),
+    Hold,
+    SoftDrop,
     HardDrop,
     Tick,
 }
 
 impl GameController {
-    pub fn new() -> io::Result<Self> {
+    pub fn new(config: Config) -> io::Result<Self> {
         let mut stdout = io::stdout().into_raw_mode()?;
         write!(stdout, "{}{}", cursor::Hide, termion::clear::All)?;
         Ok(GameController {
-            game: StandardGame::new(),
+            game: Game::new(config),
             pause: false,
+            ghost: true,
+            last_tick: Instant::now(),
             out: stdout,
         })
     }
@@ -35,17 +47,34 @@ impl GameController {
         self.pause = !self.pause;
     }
 
+    pub fn toggle_ghost(&mut self) {
+        self.ghost = !self.ghost;
+    }
+
     pub fn render(&mut self) -> io::Result<()> {
+        let ghost = self.ghost.then(|| self.game.ghost());
         self.game.draw_piece(true);
+        let (width, height) = (self.game.width(), self.game.height());
         write!(self.out, "{}", cursor::Goto(1, 1))?;
         let wall = format!("{} {}", Bg(color::White), Bg(color::Reset));
-        for i in 0..HEIGHT {
-            let row = (0..WIDTH)
-                .map(|j| format!("{}  {}", self.game.grid[i][j], Bg(color::Reset)))
+        for i in BUFFER..self.game.rows() {
+            let row = (0..width)
+                .map(|j| match &ghost {
+                    Some(ghost)
+                        if self.game.grid[i][j].is_none()
+                            && ghost.cells().contains(&(i as isize, j as isize)) =>
+                    {
+                        format!("{}[]{}", Foreground(ghost.color()), Fg(color::Reset))
+                    }
+                    _ => match self.game.grid[i][j] {
+                        Some(kind) => format!("{}  {}", kind.color, Bg(color::Reset)),
+                        None => "  ".to_string(),
+                    },
+                })
                 .collect::<String>();
             writeln!(self.out, "{}{}{}\r", wall, row, wall)?;
         }
-        let bottom = (0..=WIDTH).map(|_| "  ").collect::<String>();
+        let bottom = (0..=width).map(|_| "  ").collect::<String>();
         write!(
             self.out,
             "{}{}{}",
@@ -55,23 +84,107 @@ impl GameController {
         )?;
         writeln!(
             self.out,
-            "{} Score: {}{}{}\r",
-            cursor::Goto(1, 1 + HEIGHT as u16),
-            self.game.score,
+            "{} Level: {} Lines: {} Score: {}{}{}\r",
+            cursor::Goto(1, 1 + height as u16),
+            self.game.score.level,
+            self.game.score.lines,
+            self.game.score.points,
             Bg(color::Reset),
             Fg(color::Reset)
         )?;
+        let callout = match self.game.callout {
+            Some((clear, at)) if self.game.time < at + CALLOUT_TIME => clear.name(),
+            _ => None,
+        };
+        write!(
+            self.out,
+            "{}{}{}",
+            cursor::Goto(2, 2 + height as u16),
+            termion::clear::UntilNewline,
+            callout.unwrap_or_default()
+        )?;
         self.game.draw_piece(false);
+        let x = 2 * width as u16 + 4;
+        // every box fits the biggest piece of the set
+        let (rows, columns) = self.game.pieces().size();
+        let (rows, columns) = (rows as u16, columns as u16);
+        let next = x + 2 * columns + 2;
+        write!(
+            self.out,
+            "{}Hold{}Next",
+            cursor::Goto(x, 1),
+            cursor::Goto(next, 1)
+        )?;
+        let held = self.game.held.clone();
+        self.render_piece(x, 2, held.as_ref())?;
+        let score = &self.game.score;
+        write!(
+            self.out,
+            "{}Combo {:<4}{}B2B {:<6}",
+            cursor::Goto(x, 3 + rows),
+            score.combo.unwrap_or(0),
+            cursor::Goto(x, 4 + rows),
+            score.back_to_back.unwrap_or(0),
+        )?;
+        let preview = self.game.preview().cloned().collect::<Vec<_>>();
+        for (i, piece) in preview.iter().enumerate() {
+            self.render_piece(next, 2 + (rows + 1) * i as u16, Some(piece))?;
+        }
+        // the column right of the preview is the mode's alone, so nothing
+        // drawn later cuts its lines short
+        let status = self.game.mode().status(&self.game);
+        for (i, line) in status.iter().enumerate() {
+            debug_assert!(line.len() <= STATUS_WIDTH, "status too wide: {line}");
+            write!(
+                self.out,
+                "{}{}{}",
+                cursor::Goto(next + 2 * columns + 2, 2 + i as u16),
+                termion::clear::UntilNewline,
+                line
+            )?;
+        }
         self.out.flush()
     }
 
+    /// Draws a piece in its spawn orientation in a box to the right of the board.
+    fn render_piece(&mut self, x: u16, y: u16, piece: Option<&Tetromino>) -> io::Result<()> {
+        let (rows, columns) = self.game.pieces().size();
+        let cells = piece.map(Tetromino::cells).unwrap_or_default();
+        // pieces may not start in the top left corner of their box
+        let top = cells.iter().map(|cell| cell.0).min().unwrap_or(0);
+        let left = cells.iter().map(|cell| cell.1).min().unwrap_or(0);
+        for row in 0..rows as isize {
+            let line = (0..columns as isize)
+                .map(|col| match piece {
+                    Some(piece) if cells.contains(&(top + row, left + col)) => {
+                        format!("{}  {}", piece.color(), Bg(color::Reset))
+                    }
+                    _ => "  ".to_string(),
+                })
+                .collect::<String>();
+            write!(self.out, "{}{}", cursor::Goto(x, y + row as u16), line)?;
+        }
+        Ok(())
+    }
+
     pub fn send(&mut self, action: Action) {
+        // every action first brings the game clock up to now, so locks and
+        // finishing times happen when the key was pressed, not at the last tick
+        let now = Instant::now();
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
         if self.pause {
             return;
         }
+        self.game.advance(elapsed);
+        if self.game.over() {
+            return;
+        }
         match action {
-            Action::Turn => self.game.rotate(),
-            Action::Tick => self.game.tick(),
+            Action::Turn(rotation) => self.game.rotate(rotation),
+            Action::Tick => (),
+            Action::Hold => self.game.hold(),
+            Action::SoftDrop => self.game.soft_drop(),
             Action::HardDrop => self.game.hard_drop(),
             Action::Shift(dir) => self.game.shift(dir),
         }
@@ -81,7 +194,12 @@ impl GameController {
 impl Drop for GameController {
     #[allow(unused_must_use)]
     fn drop(&mut self) {
-        write!(self.out, "{}", cursor::Show);
+        write!(
+            self.out,
+            "{}{}",
+            cursor::Goto(1, 3 + self.game.height() as u16),
+            cursor::Show
+        );
         self.out.flush();
     }
 }
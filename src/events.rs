@@ -4,7 +4,7 @@ use std::time::Duration;
 use termion::event::Key;
 use termion::input::TermRead;
 
-const TICK_INTERVAL: Duration = Duration::from_millis(500);
+const TICK_INTERVAL: Duration = Duration::from_millis(16);
 
 pub enum Event {
     Tick,
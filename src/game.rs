@@ -1,117 +1,1120 @@
+use crate::gravity::{Gravity, MAX_G};
+use crate::modes::Mode;
+use crate::pieces::PieceSet;
+use crate::randomizer::Randomizer;
+use crate::scoring::{Clear, Score, Spin};
 use crate::tetromino::*;
-use rand::prelude::{SeedableRng, SmallRng};
+use rand::prelude::{Rng, SeedableRng, SmallRng};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Duration;
 
-pub const WIDTH: usize = 10;
-pub const HEIGHT: usize = 20;
-pub type StandardGame = Game<WIDTH, HEIGHT>;
+/// Hidden rows above the visible playfield that pieces spawn into.
+pub const BUFFER: usize = 20;
+
+/// Top row of a freshly spawned piece, two rows above the visible field.
+const SPAWN_ROW: usize = BUFFER - 2;
+
+const MAX_LOCK_RESETS: u32 = 15;
+
+/// What gives a grounded piece more time before it locks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockReset {
+    /// Every successful shift or rotation, up to `MAX_LOCK_RESETS` times.
+    Move,
+    /// Only falling to a row lower than it has been before.
+    Step,
+}
+
+impl FromStr for LockReset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "move" => Ok(Self::Move),
+            "step" => Ok(Self::Step),
+            _ => Err(format!("unknown lock reset: {name}")),
+        }
+    }
+}
+
+/// Rules picked when a game is built.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    /// Level the game starts at.
+    pub level: u32,
+    /// Columns of the board.
+    pub width: usize,
+    /// Rows of the visible playfield, not counting the buffer above it.
+    pub height: usize,
+    pub pieces: PieceSet,
+    pub randomizer: Randomizer,
+    /// Number of upcoming pieces shown ahead of time.
+    pub preview: usize,
+    pub gravity: Gravity,
+    /// How long a piece may rest on the stack before it locks.
+    pub lock_delay: Duration,
+    pub lock_reset: LockReset,
+    /// End the game when a piece locks even partly above the visible field.
+    pub partial_lock_out: bool,
+}
+
+impl Config {
+    /// Whether these are the standard rules, where only the mode and the
+    /// length of the preview are up to the player. Records are kept for
+    /// standard games only.
+    pub fn standard(&self) -> bool {
+        *self
+            == Config {
+                mode: self.mode,
+                preview: self.preview,
+                ..Config::default()
+            }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::default(),
+            level: 1,
+            width: 10,
+            height: 20,
+            pieces: PieceSet::default(),
+            randomizer: Randomizer::default(),
+            preview: 5,
+            gravity: Gravity::default(),
+            lock_delay: Duration::from_millis(500),
+            lock_reset: LockReset::Move,
+            partial_lock_out: false,
+        }
+    }
+}
+
+/// Why the game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopOut {
+    /// A new piece spawned overlapping the stack.
+    Block,
+    /// A piece locked entirely above the visible field.
+    Lock,
+    /// A piece locked partly above the visible field, with
+    /// `Config::partial_lock_out` set.
+    PartialLock,
+    /// Garbage pushed the stack or the active piece past the top of the buffer.
+    Garbage,
+}
 
 #[derive(Clone, Debug)]
-pub struct Game<const WIDTH: usize, const HEIGHT: usize> {
-    pub grid: [[Color; WIDTH]; HEIGHT],
-    pub score: u32,
+pub struct Game {
+    /// Rows from the top of the buffer down to the floor.
+    pub grid: Vec<Vec<Option<&'static PieceKind>>>,
+    pub score: Score,
+    /// What the most recent lock achieved.
+    pub clear: Clear,
+    /// The latest clear worth a callout, and when it happened.
+    pub callout: Option<(Clear, Duration)>,
     pub tetromino: Tetromino,
-    pub over: bool,
+    pub held: Option<Tetromino>,
+    pub can_hold: bool,
+    pub top_out: Option<TopOut>,
+    /// Game time elapsed so far, driven by `advance`.
+    pub time: Duration,
+    /// Rows gravity owes the piece, carried over between updates.
+    fall: f64,
+    gravity: Gravity,
+    lock_timer: Duration,
+    lock_resets: u32,
+    lowest: isize,
+    /// Rotation and kick test of the last move, if it was a rotation.
+    spun: Option<(Rotation, usize)>,
+    mode: Mode,
+    lock_delay: Duration,
+    lock_reset: LockReset,
+    partial_lock_out: bool,
+    queue: VecDeque<Tetromino>,
+    pieces: PieceSet,
+    randomizer: Randomizer,
     rng: SmallRng,
+    /// Garbage rows pushed in so far.
+    garbage: u32,
+    /// Picks garbage holes apart from `rng`, so garbage never changes the pieces dealt.
+    garbage_rng: SmallRng,
 }
 
-impl<const WIDTH: usize, const HEIGHT: usize> Game<WIDTH, HEIGHT> {
-    pub fn new_with_seed(seed: u64) -> Self {
-        let mut rng = SmallRng::seed_from_u64(seed);
-        Game {
-            grid: [[Color::None; WIDTH]; HEIGHT],
-            score: 0,
-            over: false,
-            tetromino: Tetromino::new_with_rng(WIDTH, &mut rng),
-            rng,
+impl Game {
+    pub fn with_config(seed: u64, config: Config) -> Self {
+        let mut game = Game {
+            grid: vec![vec![None; config.width]; config.height + BUFFER],
+            score: Score::new(config.level),
+            clear: Clear::NONE,
+            callout: None,
+            top_out: None,
+            tetromino: Tetromino::new(PieceKind::I),
+            held: None,
+            can_hold: true,
+            time: Duration::ZERO,
+            fall: 0.0,
+            gravity: config.gravity,
+            lock_timer: Duration::ZERO,
+            lock_resets: 0,
+            lowest: 0,
+            spun: None,
+            mode: config.mode,
+            lock_delay: config.lock_delay,
+            lock_reset: config.lock_reset,
+            partial_lock_out: config.partial_lock_out,
+            queue: VecDeque::with_capacity(config.preview + 1),
+            pieces: config.pieces,
+            randomizer: config.randomizer,
+            rng: SmallRng::seed_from_u64(seed),
+            garbage: 0,
+            garbage_rng: SmallRng::seed_from_u64(seed.wrapping_add(1)),
+        };
+        game.randomizer.prime(&game.pieces);
+        for _ in 0..config.preview {
+            let piece = game.deal();
+            game.queue.push_back(piece);
         }
+        let mode = game.mode;
+        mode.start(&mut game);
+        let first = game.next_piece();
+        game.spawn(first);
+        game
+    }
+
+    #[cfg(test)]
+    pub fn new_with_seed(seed: u64) -> Self {
+        Self::with_config(seed, Config::default())
+    }
+
+    pub fn new(config: Config) -> Self {
+        Self::with_config(rand::random(), config)
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid[0].len()
     }
 
-    pub fn new() -> Self {
-        Self::new_with_seed(rand::random())
+    /// Rows of the whole grid, buffer included.
+    pub fn rows(&self) -> usize {
+        self.grid.len()
+    }
+
+    /// Rows of the visible playfield.
+    pub fn height(&self) -> usize {
+        self.rows() - BUFFER
+    }
+
+    pub fn pieces(&self) -> &PieceSet {
+        &self.pieces
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Whether the game has ended, by topping out or reaching the goal of its mode.
+    pub fn over(&self) -> bool {
+        self.top_out.is_some() || self.mode.finished(self)
+    }
+
+    /// Upcoming pieces in the order they will spawn.
+    pub fn preview(&self) -> impl Iterator<Item = &Tetromino> {
+        self.queue.iter()
     }
 
     pub fn draw_piece(&mut self, draw: bool) {
-        for cell in &self.tetromino.cells {
-            self.grid[cell.0][cell.1] = if draw {
-                self.tetromino.color
-            } else {
-                Color::None
-            }
+        for (y, x) in self.tetromino.cells() {
+            self.grid[y as usize][x as usize] = draw.then_some(self.tetromino.kind);
         }
     }
 
-    fn clear_lines(&mut self) {
-        for i in 0..HEIGHT {
+    fn clear_lines(&mut self) -> u32 {
+        let mut cleared = 0;
+        for i in 0..self.rows() {
             let full = self.grid[i].iter().all(|x| x.is_some());
             if !full {
                 continue;
             }
-            self.score += 1;
-            for k in (1..=i).rev() {
-                let prev_row = self.grid[k - 1];
-                self.grid[k] = prev_row;
+            cleared += 1;
+            self.grid.remove(i);
+            self.grid.insert(0, vec![None; self.width()]);
+        }
+        cleared
+    }
+
+    /// Pushes `rows` rows of garbage in from the bottom, each full but for one
+    /// hole. The hole starts in column `hole`, or a random one if `None`, and
+    /// moves to another column between rows with probability `messiness`.
+    /// Returns the hole of the bottom row, so more garbage can carry on
+    /// from it. `messiness` is clamped to between 0 and 1, NaN counting as 0.
+    ///
+    /// Panics if `hole` is not a column of the board.
+    pub fn add_garbage(&mut self, rows: usize, hole: Option<usize>, messiness: f64) -> usize {
+        let width = self.width();
+        if let Some(hole) = hole {
+            assert!(hole < width, "garbage hole {hole} is off the board");
+        }
+        let messiness = if messiness.is_nan() {
+            0.0
+        } else {
+            messiness.clamp(0.0, 1.0)
+        };
+        let mut hole = hole.unwrap_or_else(|| self.garbage_rng.gen_range(0, width));
+        for row in 0..rows {
+            if row > 0 && width > 1 && self.garbage_rng.gen_bool(messiness) {
+                // any column but the one the hole is in
+                hole = (hole + self.garbage_rng.gen_range(1, width)) % width;
+            }
+            let top = self.grid.remove(0);
+            if top.iter().any(Option::is_some) {
+                self.top_out = Some(TopOut::Garbage);
+            }
+            let mut line = vec![Some(PieceKind::GARBAGE); width];
+            line[hole] = None;
+            self.grid.push(line);
+        }
+        self.garbage += rows as u32;
+        // the active piece rides up on the stack when it would overlap it
+        while !fits(&self.grid, &self.tetromino) {
+            if self.tetromino.cells().iter().any(|cell| cell.0 <= 0) {
+                self.top_out = Some(TopOut::Garbage);
+                break;
+            }
+            self.tetromino.origin.0 -= 1;
+        }
+        hole
+    }
+
+    /// Rows that still hold garbage.
+    pub fn garbage_left(&self) -> u32 {
+        let garbage = |cell: &Option<&PieceKind>| *cell == Some(PieceKind::GARBAGE);
+        self.grid
+            .iter()
+            .filter(|row| row.iter().any(garbage))
+            .count() as u32
+    }
+
+    /// Garbage rows pushed in so far.
+    pub fn garbage_added(&self) -> u32 {
+        self.garbage
+    }
+
+    /// Garbage rows cleared so far.
+    pub fn garbage_cleared(&self) -> u32 {
+        self.garbage - self.garbage_left()
+    }
+
+    /// Applies the 3-corner rule to a T piece whose last move was a rotation.
+    fn spin(&self) -> Spin {
+        let (rotation, test) = match self.spun {
+            Some(spun) if self.tetromino.kind.spin => spun,
+            _ => return Spin::None,
+        };
+        let (y, x) = self.tetromino.center();
+        // clockwise from the top left, walls and floor count as filled
+        let corners = [(-1, -1), (-1, 1), (1, 1), (1, -1)].map(|(dy, dx): (isize, isize)| {
+            let (y, x) = (y + dy, x + dx);
+            y < 0 || x < 0 || y >= self.rows() as isize || x >= self.width() as isize || {
+                self.grid[y as usize][x as usize].is_some()
             }
-            self.grid[0] = [Color::None; WIDTH];
+        });
+        // the two corners on the side the T points to
+        let front = self.tetromino.orientation as usize;
+        let front = corners[front] as u32 + corners[(front + 1) % 4] as u32;
+        let back = corners.iter().filter(|&&c| c).count() as u32 - front;
+        // the last SRS kick test turns a would-be Mini into a full T-spin
+        let last_kick = test == 4 && rotation != Rotation::Half;
+        match (front, back) {
+            (2, 1..) => Spin::Full,
+            (1, 2) if last_kick => Spin::Full,
+            (1, 2) => Spin::Mini,
+            _ => Spin::None,
         }
     }
 
     pub fn shift(&mut self, dir: Direction) {
-        let (left, right, down) = self.piece_touches();
-        let touch = match dir {
-            Direction::Left => left,
-            Direction::Right => right,
-            Direction::Down => down,
+        let shifted = self.tetromino.shifted(dir);
+        if !fits(&self.grid, &shifted) {
+            return;
+        }
+        let grounded = self.grounded();
+        self.tetromino = shifted;
+        self.spun = None;
+        match dir {
+            Direction::Down => self.track_lowest(),
+            _ if grounded => self.reset_lock(),
+            _ => (),
+        }
+    }
+
+    fn grounded(&self) -> bool {
+        !fits(&self.grid, &self.tetromino.shifted(Direction::Down))
+    }
+
+    /// Gives a grounded piece a fresh lock delay after a successful move.
+    fn reset_lock(&mut self) {
+        if self.lock_reset == LockReset::Move && self.lock_resets < MAX_LOCK_RESETS {
+            self.lock_timer = Duration::ZERO;
+            self.lock_resets += 1;
+        }
+    }
+
+    /// Restarts the lock delay once the piece gets lower than ever before.
+    fn track_lowest(&mut self) {
+        let bottom = self
+            .tetromino
+            .cells()
+            .iter()
+            .map(|cell| cell.0)
+            .max()
+            .unwrap();
+        if bottom > self.lowest {
+            self.lowest = bottom;
+            self.lock_timer = Duration::ZERO;
+            self.lock_resets = 0;
+        }
+    }
+
+    pub fn rotate(&mut self, rotation: Rotation) {
+        let grounded = self.grounded();
+        let grid = &self.grid;
+        let rotated = self.tetromino.rotate(rotation, |piece| fits(grid, piece));
+        if let Some(test) = rotated {
+            self.spun = Some((rotation, test));
+            if grounded {
+                self.reset_lock();
+            }
+            self.track_lowest();
+        }
+    }
+
+    /// Swaps the active piece with the held one, or with the next piece if
+    /// nothing is held yet. Allowed once per piece until it locks.
+    pub fn hold(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        let current = Tetromino::new(self.tetromino.kind);
+        let next = match self.held.replace(current) {
+            Some(held) => held.spawned(self.width()),
+            None => self.next_piece(),
         };
-        if !touch {
-            self.tetromino.shift(dir);
+        self.spawn(next);
+        self.can_hold = false;
+    }
+
+    fn deal(&mut self) -> Tetromino {
+        let index = self.randomizer.next(self.pieces.len(), &mut self.rng);
+        Tetromino::new(self.pieces.0[index])
+    }
+
+    fn next_piece(&mut self) -> Tetromino {
+        let piece = self.deal();
+        self.queue.push_back(piece);
+        let next = self.queue.pop_front().unwrap();
+        next.spawned(self.width())
+    }
+
+    /// Puts a piece fresh out of `spawned` just above the visible field
+    /// and lets it drop one row right away if nothing is in the way.
+    fn spawn(&mut self, mut tetromino: Tetromino) {
+        tetromino.origin.0 += SPAWN_ROW as isize;
+        self.tetromino = tetromino;
+        self.fall = 0.0;
+        self.lock_timer = Duration::ZERO;
+        self.lock_resets = 0;
+        self.lowest = 0;
+        self.spun = None;
+        if !fits(&self.grid, &self.tetromino) {
+            self.top_out = Some(TopOut::Block);
+            return;
         }
+        self.shift(Direction::Down);
+        self.track_lowest();
     }
 
-    fn piece_touches(&self) -> (bool, bool, bool) {
-        let cells = self.tetromino.cells;
-        let left = cells
+    /// Where the active piece would land after a hard drop.
+    pub fn ghost(&self) -> Tetromino {
+        let mut ghost = self.tetromino.clone();
+        while fits(&self.grid, &ghost.shifted(Direction::Down)) {
+            ghost.shift(Direction::Down);
+        }
+        ghost
+    }
+
+    /// Moves the piece one row down ahead of gravity. Unlike a hard drop
+    /// it leaves the piece free to slide until gravity locks it.
+    pub fn soft_drop(&mut self) {
+        if !self.grounded() {
+            self.shift(Direction::Down);
+            self.score.soft_drop(1);
+        }
+    }
+
+    /// Drops the piece as far as it goes and locks it right away.
+    pub fn hard_drop(&mut self) {
+        let ghost = self.ghost();
+        let rows = ghost.origin.0 - self.tetromino.origin.0;
+        self.score.hard_drop(rows as u32);
+        if rows > 0 {
+            self.spun = None;
+        }
+        self.tetromino = ghost;
+        self.lock();
+    }
+
+    /// Moves the game clock forward, pulling the piece down with gravity
+    /// and locking it once it has rested on the stack for the lock delay.
+    pub fn advance(&mut self, dt: Duration) {
+        if self.over() {
+            return;
+        }
+        // the clock stops right at the time limit
+        let dt = match self.mode.time_limit() {
+            Some(limit) => dt.min(limit.saturating_sub(self.time)),
+            None => dt,
+        };
+        self.time += dt;
+        let mode = self.mode;
+        mode.advanced(self);
+        if self.over() {
+            return;
+        }
+        let level = self.score.level;
+        if self.gravity.g(level) >= MAX_G {
+            self.fall = self.rows() as f64;
+        } else {
+            self.fall += self.gravity.rows(level, dt.as_secs_f64());
+        }
+        while self.fall >= 1.0 && !self.grounded() {
+            self.fall -= 1.0;
+            self.shift(Direction::Down);
+        }
+        if self.grounded() {
+            self.fall = 0.0;
+            self.lock_timer += dt;
+            if self.lock_timer >= self.lock_delay {
+                self.lock();
+            }
+        }
+    }
+
+    fn lock(&mut self) {
+        let spin = self.spin();
+        self.draw_piece(true);
+        let cells = self.tetromino.cells();
+        let hidden = cells.iter().filter(|cell| cell.0 < BUFFER as isize).count();
+        if hidden == cells.len() {
+            self.top_out = Some(TopOut::Lock);
+            return;
+        }
+        if hidden > 0 && self.partial_lock_out {
+            self.top_out = Some(TopOut::PartialLock);
+            return;
+        }
+        let rows = self.clear_lines();
+        let perfect = self.grid.iter().flatten().all(|cell| cell.is_none());
+        self.clear = self.score.clear(rows, spin, perfect);
+        if self.clear.name().is_some() {
+            self.callout = Some((self.clear, self.time));
+        }
+        let next = self.next_piece();
+        self.spawn(next);
+        self.can_hold = true;
+        let mode = self.mode;
+        mode.locked(self);
+    }
+}
+
+/// Whether every cell of `piece` is on `grid` and empty.
+fn fits(grid: &[Vec<Option<&PieceKind>>], piece: &Tetromino) -> bool {
+    piece.cells().iter().all(|&(y, x)| {
+        (0..grid.len() as isize).contains(&y)
+            && (0..grid[0].len() as isize).contains(&x)
+            && grid[y as usize][x as usize].is_none()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 10;
+    const ROWS: usize = 20 + BUFFER;
+
+    #[test]
+    fn hold_once_per_piece() {
+        let mut game = Game::new_with_seed(1);
+        let first = game.tetromino.kind;
+        game.hold();
+        assert_eq!(game.held.as_ref().unwrap().kind, first);
+        let second = game.tetromino.kind;
+        game.hold();
+        assert_eq!(game.tetromino.kind, second);
+        game.hard_drop();
+        game.hold();
+        assert_eq!(game.tetromino.kind, first);
+        assert_eq!(game.tetromino.orientation, Orientation::Spawn);
+        assert!(game
+            .tetromino
+            .cells()
             .iter()
-            .any(|cell| cell.1 == 0 || self.grid[cell.0][cell.1 - 1].is_some());
-        let right = cells
+            .any(|cell| cell.0 == SPAWN_ROW as isize + 1));
+    }
+
+    #[test]
+    fn scores_clears_and_drops() {
+        let mut game = Game::new_with_seed(5);
+        for cell in game.grid[ROWS - 1].iter_mut().skip(4) {
+            *cell = Some(PieceKind::Z);
+        }
+        game.tetromino = Tetromino::new(PieceKind::I);
+        game.soft_drop();
+        game.hard_drop();
+        assert_eq!(game.score.lines, 1);
+        // the flat I sits on the second row of its box
+        assert_eq!(game.score.points, 1 + 2 * (ROWS as u32 - 3) + 100 + 800);
+        assert!(game.grid.iter().flatten().all(|cell| cell.is_none()));
+        assert!(game.clear.perfect);
+        assert_eq!(game.score.perfect_clears, 1);
+    }
+
+    #[test]
+    fn leftovers_spoil_perfect_clear() {
+        let mut game = Game::new_with_seed(5);
+        for cell in game.grid[ROWS - 1].iter_mut().skip(4) {
+            *cell = Some(PieceKind::Z);
+        }
+        game.grid[0][WIDTH - 1] = Some(PieceKind::Z);
+        game.tetromino = Tetromino::new(PieceKind::I);
+        game.hard_drop();
+        assert_eq!(game.score.lines, 1);
+        assert!(!game.clear.perfect);
+        assert!(game.grid[1][WIDTH - 1].is_some());
+    }
+
+    #[test]
+    fn narrow_board() {
+        let config = Config {
+            width: 4,
+            height: 40,
+            ..Config::default()
+        };
+        let mut game = Game::with_config(5, config);
+        assert_eq!((game.width(), game.height()), (4, 40));
+        assert_eq!(game.rows(), 40 + BUFFER);
+        game.tetromino = Tetromino::new(PieceKind::I).spawned(game.width());
+        game.hard_drop();
+        assert_eq!(game.score.lines, 1);
+        assert!(game.clear.perfect);
+        assert!(!game.over());
+    }
+
+    #[test]
+    fn pentominos() {
+        let config = Config {
+            pieces: PieceSet::pentominos(),
+            ..Config::default()
+        };
+        let mut game = Game::with_config(4, config);
+        for drops in 1..=8 {
+            game.hard_drop();
+            let filled = game
+                .grid
+                .iter()
+                .flatten()
+                .filter(|cell| cell.is_some())
+                .count();
+            assert_eq!(filled, 5 * drops - WIDTH * game.score.lines as usize);
+        }
+        assert_eq!(game.tetromino.cells().len(), 5);
+    }
+
+    fn t_piece_at(game: &mut Game, rows: usize, rotation: Option<Rotation>) {
+        let mut t = Tetromino::new(PieceKind::T);
+        if let Some(rotation) = rotation {
+            t.rotate(rotation, |_| true);
+        }
+        for _ in 0..rows {
+            t.shift(Direction::Down);
+        }
+        game.tetromino = t;
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut game = Game::new_with_seed(7);
+        for x in 0..WIDTH {
+            game.grid[ROWS - 1][x] = if x == 1 { None } else { Some(PieceKind::Z) };
+            game.grid[ROWS - 2][x] = if x < 3 { None } else { Some(PieceKind::Z) };
+        }
+        game.grid[ROWS - 3][0] = Some(PieceKind::Z);
+        t_piece_at(&mut game, ROWS - 3, Some(Rotation::Clockwise));
+        game.rotate(Rotation::Clockwise);
+        assert_eq!(game.tetromino.orientation, Orientation::Flip);
+        game.hard_drop();
+        assert_eq!((game.clear.rows, game.clear.spin), (2, Spin::Full));
+        assert_eq!(game.callout, Some((game.clear, Duration::ZERO)));
+        assert_eq!(game.score.points, 1200);
+    }
+
+    #[test]
+    fn t_spin_mini() {
+        let mut game = Game::new_with_seed(7);
+        for x in 0..WIDTH {
+            game.grid[ROWS - 1][x] = if x == 1 { None } else { Some(PieceKind::Z) };
+        }
+        game.grid[ROWS - 3][2] = Some(PieceKind::Z);
+        t_piece_at(&mut game, ROWS - 3, None);
+        game.rotate(Rotation::CounterClockwise);
+        assert_eq!(game.tetromino.orientation, Orientation::Left);
+        game.hard_drop();
+        assert_eq!((game.clear.rows, game.clear.spin), (1, Spin::Mini));
+        assert_eq!(game.score.points, 200);
+    }
+
+    #[test]
+    fn no_spin_after_dropping() {
+        let mut game = Game::new_with_seed(7);
+        for x in 0..WIDTH {
+            game.grid[ROWS - 1][x] = if x == 1 { None } else { Some(PieceKind::Z) };
+        }
+        game.grid[ROWS - 3][2] = Some(PieceKind::Z);
+        t_piece_at(&mut game, ROWS - 4, Some(Rotation::CounterClockwise));
+        game.rotate(Rotation::Half);
+        game.rotate(Rotation::Half);
+        assert_eq!(game.tetromino.orientation, Orientation::Left);
+        game.soft_drop();
+        game.hard_drop();
+        assert_eq!((game.clear.rows, game.clear.spin), (1, Spin::None));
+    }
+
+    #[test]
+    fn soft_drop_does_not_lock() {
+        let mut game = Game::new_with_seed(2);
+        let landed = game.ghost().cells();
+        for _ in 0..ROWS {
+            game.soft_drop();
+        }
+        assert_eq!(game.tetromino.cells(), landed);
+        assert!(game.grid.iter().flatten().all(|cell| cell.is_none()));
+        game.shift(Direction::Left);
+        game.advance(Duration::from_millis(500));
+        assert_eq!(
+            game.grid
+                .iter()
+                .flatten()
+                .filter(|cell| cell.is_some())
+                .count(),
+            4
+        );
+    }
+
+    fn landed(lock_reset: LockReset) -> Game {
+        let config = Config {
+            lock_reset,
+            ..Config::default()
+        };
+        let mut game = Game::with_config(4, config);
+        for _ in 0..WIDTH {
+            game.shift(Direction::Left);
+        }
+        for _ in 0..3 {
+            game.shift(Direction::Right);
+        }
+        for _ in 0..ROWS {
+            game.soft_drop();
+        }
+        game
+    }
+
+    #[test]
+    fn gravity() {
+        let config = Config {
+            gravity: Gravity::Custom(vec![0.5]),
+            ..Config::default()
+        };
+        let mut game = Game::with_config(6, config);
+        let top = game.tetromino.cells()[0].0;
+        for _ in 0..4 {
+            game.advance(Duration::from_millis(17));
+        }
+        assert_eq!(game.tetromino.cells()[0].0, top + 2);
+
+        let config = Config {
+            gravity: Gravity::Custom(vec![MAX_G]),
+            ..Config::default()
+        };
+        let mut game = Game::with_config(6, config);
+        let landed = game.ghost().cells();
+        game.advance(Duration::from_millis(1));
+        assert_eq!(game.tetromino.cells(), landed);
+    }
+
+    fn locked(game: &Game) -> bool {
+        game.grid.iter().flatten().any(|cell| cell.is_some())
+    }
+
+    #[test]
+    fn lock_delay() {
+        let mut game = landed(LockReset::Move);
+        game.advance(Duration::from_millis(499));
+        assert!(!locked(&game));
+        game.advance(Duration::from_millis(1));
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn move_reset() {
+        let mut game = landed(LockReset::Move);
+        for i in 0..MAX_LOCK_RESETS {
+            game.advance(Duration::from_millis(400));
+            assert!(!locked(&game));
+            game.shift(if i % 2 == 0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            });
+        }
+        game.advance(Duration::from_millis(400));
+        assert!(!locked(&game));
+        game.shift(Direction::Left);
+        game.advance(Duration::from_millis(100));
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn step_reset() {
+        let mut game = landed(LockReset::Step);
+        game.advance(Duration::from_millis(400));
+        game.shift(Direction::Left);
+        game.advance(Duration::from_millis(100));
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn hard_drop_locks() {
+        let mut game = Game::new_with_seed(2);
+        let kind = game.tetromino.kind;
+        let landed = game.ghost().cells();
+        game.hard_drop();
+        assert!(landed
             .iter()
-            .any(|cell| cell.1 == WIDTH - 1 || self.grid[cell.0][cell.1 + 1].is_some());
-        let down = cells
+            .all(|&(y, x)| game.grid[y as usize][x as usize] == Some(kind)));
+        assert!(game
+            .tetromino
+            .cells()
             .iter()
-            .any(|cell| cell.0 == HEIGHT - 1 || self.grid[cell.0 + 1][cell.1].is_some());
-        (left, right, down)
+            .any(|cell| cell.0 == SPAWN_ROW as isize + 1));
     }
 
-    pub fn rotate(&mut self) {
-        let backup = self.tetromino.cells;
-        if self.tetromino.rotate().is_some() {
-            let in_bounds = self.tetromino.cells.iter().all(|cell| {
-                cell.0 < HEIGHT && cell.1 < WIDTH && self.grid[cell.0][cell.1].is_none()
-            });
-            if in_bounds {
-                return;
+    fn stack(game: &mut Game, from: usize) {
+        for row in game.grid[from..].iter_mut() {
+            for cell in row.iter_mut().take(WIDTH - 1) {
+                *cell = Some(PieceKind::Z);
             }
         }
-        self.tetromino.cells = backup;
     }
 
-    pub fn hard_drop(&mut self) {
-        while !self.piece_touches().2 {
-            self.shift(Direction::Down);
+    #[test]
+    fn spawns_into_view() {
+        let mut game = Game::new_with_seed(2);
+        for kind in PieceKind::ALL {
+            game.spawn(Tetromino::new(kind).spawned(WIDTH));
+            // every tetromino shows its bottom row on the top visible row
+            let bottom = game.tetromino.cells().iter().map(|cell| cell.0).max();
+            assert_eq!(bottom, Some(BUFFER as isize), "{}", kind.name);
+        }
+        for kind in PieceSet::big().0 {
+            game.spawn(Tetromino::new(kind).spawned(WIDTH));
+            let cells = game.tetromino.cells();
+            assert!(
+                cells.iter().any(|cell| cell.0 >= BUFFER as isize),
+                "{}",
+                kind.name
+            );
         }
     }
 
-    pub fn tick(&mut self) {
-        if !self.piece_touches().2 {
-            self.shift(Direction::Down);
-        } else {
-            self.draw_piece(true);
-            self.clear_lines();
-            self.tetromino = Tetromino::new_with_rng(WIDTH, &mut self.rng);
-            self.over = self
-                .tetromino
-                .cells
+    #[test]
+    fn block_out() {
+        let mut game = Game::new_with_seed(2);
+        stack(&mut game, SPAWN_ROW);
+        game.hold();
+        assert_eq!(game.top_out, Some(TopOut::Block));
+    }
+
+    #[test]
+    fn lock_out() {
+        let mut game = Game::new_with_seed(2);
+        stack(&mut game, BUFFER);
+        t_piece_at(&mut game, 0, None);
+        game.hard_drop();
+        assert_eq!(game.top_out, Some(TopOut::Lock));
+    }
+
+    #[test]
+    fn partial_lock_out() {
+        for partial_lock_out in [false, true] {
+            let config = Config {
+                partial_lock_out,
+                ..Config::default()
+            };
+            let mut game = Game::with_config(2, config);
+            stack(&mut game, BUFFER + 1);
+            t_piece_at(&mut game, 0, None);
+            game.hard_drop();
+            let top_out = partial_lock_out.then_some(TopOut::PartialLock);
+            assert_eq!(game.top_out, top_out);
+        }
+    }
+
+    #[test]
+    fn preview_comes_next() {
+        for preview in 0..=6 {
+            let config = Config {
+                preview,
+                ..Config::default()
+            };
+            let mut game = Game::with_config(3, config.clone());
+            let mut replay = Game::with_config(3, config);
+            assert_eq!(game.preview().count(), preview);
+            for _ in 0..10 {
+                let upcoming = game.preview().map(|t| t.kind).collect::<Vec<_>>();
+                game.hard_drop();
+                if let Some(&next) = upcoming.first() {
+                    assert_eq!(game.tetromino.kind, next);
+                }
+                replay.hard_drop();
+                assert_eq!(game.tetromino.cells(), replay.tetromino.cells());
+            }
+        }
+    }
+
+    #[test]
+    fn garbage_rows() {
+        let mut game = Game::new_with_seed(4);
+        game.grid[ROWS - 1][0] = Some(PieceKind::Z);
+        assert_eq!(game.add_garbage(3, Some(2), 0.0), 2);
+        assert_eq!(game.grid[ROWS - 4][0], Some(PieceKind::Z));
+        for row in &game.grid[ROWS - 3..] {
+            for (col, cell) in row.iter().enumerate() {
+                assert_eq!(cell.is_none(), col == 2);
+            }
+        }
+        assert!(game.grid[ROWS - 1][0].unwrap().color == Color::Gray);
+        assert!(!game.over());
+    }
+
+    #[test]
+    fn messy_garbage() {
+        let mut game = Game::new_with_seed(4);
+        let bottom = game.add_garbage(6, None, 1.0);
+        let holes = game.grid[ROWS - 6..]
+            .iter()
+            .map(|row| row.iter().position(Option::is_none).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(holes[5], bottom);
+        assert!(holes.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn garbage_messiness_is_clamped() {
+        let holes = |messiness| {
+            let mut game = Game::new_with_seed(4);
+            game.add_garbage(6, Some(3), messiness);
+            game.grid[ROWS - 6..]
                 .iter()
-                .any(|cell| self.grid[cell.0][cell.1].is_some());
+                .map(|row| row.iter().position(Option::is_none).unwrap())
+                .collect::<Vec<_>>()
+        };
+        for messiness in [-1.0, f64::NAN] {
+            assert_eq!(holes(messiness), [3; 6]);
+        }
+        assert!(holes(2.0).windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "off the board")]
+    fn garbage_hole_off_the_board() {
+        Game::new_with_seed(4).add_garbage(1, Some(WIDTH), 0.0);
+    }
+
+    #[test]
+    fn garbage_pushes_piece_up() {
+        let mut game = Game::new_with_seed(4);
+        game.tetromino = game.ghost();
+        let cells = game.tetromino.cells();
+        game.add_garbage(2, Some(WIDTH - 1), 0.0);
+        assert!(fits(&game.grid, &game.tetromino));
+        let pushed = cells.iter().map(|&(y, x)| (y - 2, x)).collect::<Vec<_>>();
+        assert_eq!(game.tetromino.cells(), pushed);
+    }
+
+    #[test]
+    fn garbage_out() {
+        let mut game = Game::new_with_seed(4);
+        game.grid[0][0] = Some(PieceKind::Z);
+        game.add_garbage(1, Some(0), 0.0);
+        assert_eq!(game.top_out, Some(TopOut::Garbage));
+
+        let mut game = Game::new_with_seed(4);
+        game.add_garbage(ROWS, Some(0), 0.0);
+        assert_eq!(game.top_out, Some(TopOut::Garbage));
+    }
+
+    #[test]
+    fn sprint_ends_at_target() {
+        let config = Config {
+            mode: Mode::Sprint { lines: 2 },
+            ..Config::default()
+        };
+        let mut game = Game::with_config(5, config);
+        for row in game.grid[ROWS - 2..].iter_mut() {
+            for cell in row.iter_mut().skip(1) {
+                *cell = Some(PieceKind::Z);
+            }
         }
+        game.tetromino = Tetromino::new(PieceKind::I);
+        game.rotate(Rotation::Clockwise);
+        for _ in 0..WIDTH {
+            game.shift(Direction::Left);
+        }
+        game.advance(Duration::from_millis(100));
+        game.hard_drop();
+        assert_eq!(game.score.lines, 2);
+        assert!(game.over());
+        assert_eq!(game.top_out, None);
+        let time = game.time;
+        game.advance(Duration::from_secs(1));
+        assert_eq!(game.time, time);
+    }
+
+    #[test]
+    fn ultra_ends_on_time() {
+        let limit = Duration::from_secs(2);
+        let config = Config {
+            mode: Mode::Ultra { limit },
+            ..Config::default()
+        };
+        let mut game = Game::with_config(5, config);
+        game.advance(Duration::from_millis(1500));
+        game.hard_drop();
+        assert!(!game.over());
+        game.advance(Duration::from_millis(1500));
+        assert!(game.over());
+        assert_eq!(game.top_out, None);
+        assert_eq!(game.time, limit);
+        assert_eq!(game.mode().record(&game), Some(game.score.points as u64));
+    }
+
+    #[test]
+    fn marathon_from_a_level() {
+        let config = Config {
+            mode: Mode::Marathon { lines: 4 },
+            level: 5,
+            ..Config::default()
+        };
+        let mut game = Game::with_config(5, config);
+        assert_eq!(game.score.level, 5);
+        stack(&mut game, ROWS - 4);
+        game.tetromino = Tetromino::new(PieceKind::I);
+        game.rotate(Rotation::Clockwise);
+        for _ in 0..WIDTH {
+            game.shift(Direction::Right);
+        }
+        game.hard_drop();
+        assert_eq!(game.score.line_clears, [0, 0, 0, 1]);
+        assert!(game.over());
+        assert_eq!(game.top_out, None);
+    }
+
+    /// Drops a vertical I into the hole of the bottom garbage row.
+    fn dig(game: &mut Game) {
+        let hole = game.grid[ROWS - 1]
+            .iter()
+            .position(Option::is_none)
+            .unwrap();
+        game.tetromino = Tetromino::new(PieceKind::I).spawned(WIDTH);
+        game.rotate(Rotation::Clockwise);
+        for _ in 0..WIDTH {
+            game.shift(Direction::Left);
+        }
+        for _ in 0..hole {
+            game.shift(Direction::Right);
+        }
+        game.hard_drop();
+    }
+
+    #[test]
+    fn cheese() {
+        for infinite in [false, true] {
+            let config = Config {
+                mode: Mode::Cheese { rows: 1, infinite },
+                ..Config::default()
+            };
+            let mut game = Game::with_config(6, config);
+            assert_eq!(game.garbage_left(), 1);
+            dig(&mut game);
+            assert_eq!(game.garbage_cleared(), 1);
+            assert_eq!(game.garbage_left(), infinite as u32);
+            assert_eq!(game.over(), !infinite);
+            assert_eq!(game.top_out, None);
+        }
+    }
+
+    #[test]
+    fn survival_garbage_rises() {
+        let config = Config {
+            mode: Mode::Survival,
+            ..Config::default()
+        };
+        let mut game = Game::with_config(6, config);
+        game.advance(Duration::from_millis(4900));
+        assert_eq!(game.garbage_added(), 0);
+        game.advance(Duration::from_millis(100));
+        assert_eq!(game.garbage_added(), 1);
+        assert_eq!(game.garbage_left(), 1);
+        while !game.over() {
+            game.advance(Duration::from_millis(100));
+        }
+        assert!(game.garbage_added() > 1);
+        assert!(game.mode().record(&game).is_some());
+    }
+
+    #[test]
+    fn standard_rules() {
+        let standard = Config {
+            mode: Mode::Sprint { lines: 40 },
+            preview: 3,
+            ..Config::default()
+        };
+        assert!(standard.standard());
+        let custom = [
+            Config {
+                width: 4,
+                ..standard.clone()
+            },
+            Config {
+                pieces: PieceSet::small(),
+                ..standard.clone()
+            },
+            Config {
+                randomizer: Randomizer::nes(),
+                ..standard.clone()
+            },
+            Config {
+                level: 5,
+                ..standard.clone()
+            },
+            Config {
+                lock_delay: Duration::from_millis(200),
+                ..standard
+            },
+        ];
+        assert!(custom.iter().all(|config| !config.standard()));
     }
 }
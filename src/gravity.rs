@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+/// Frames per second that gravity is expressed in: 1G is one row per frame.
+const FPS: f64 = 60.0;
+const NES_FPS: f64 = 60.0988;
+/// At this speed and beyond pieces land the moment they spawn.
+pub const MAX_G: f64 = 20.0;
+
+/// Frames per row on NES levels 0 to 29 and above.
+const NES_FRAMES: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    1,
+];
+
+/// How fast pieces fall as the level goes up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Gravity {
+    /// Tetris guideline: `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row.
+    #[default]
+    Guideline,
+    /// The NES frame table, with level 1 here being level 0 there.
+    Nes,
+    /// Gravity in G for each level from 1 on; the last entry holds from there.
+    Custom(Vec<f64>),
+}
+
+impl Gravity {
+    /// Gravity at `level` in G, that is rows per 1/60 of a second.
+    pub fn g(&self, level: u32) -> f64 {
+        let level = level.max(1) - 1;
+        let g = match self {
+            Self::Guideline => {
+                let level = level.min(19) as f64;
+                let seconds = (0.8 - level * 0.007).powf(level);
+                1.0 / (seconds * FPS)
+            }
+            Self::Nes => {
+                let frames = NES_FRAMES[(level as usize).min(NES_FRAMES.len() - 1)];
+                NES_FPS / frames as f64 / FPS
+            }
+            Self::Custom(curve) => curve[(level as usize).min(curve.len() - 1)],
+        };
+        g.min(MAX_G)
+    }
+
+    /// Rows a piece falls in `seconds` at `level`.
+    pub fn rows(&self, level: u32, seconds: f64) -> f64 {
+        self.g(level) * FPS * seconds
+    }
+}
+
+impl FromStr for Gravity {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "guideline" => Ok(Self::Guideline),
+            "nes" => Ok(Self::Nes),
+            _ => {
+                let curve = name
+                    .strip_prefix("custom:")
+                    .ok_or_else(|| format!("unknown gravity: {name}"))?;
+                let curve = curve
+                    .split(',')
+                    .map(|g| match g.parse::<f64>() {
+                        Ok(g) if g > 0.0 => Ok(g),
+                        _ => Err(format!("invalid gravity: {g}")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self::Custom(curve))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guideline() {
+        let gravity = Gravity::Guideline;
+        assert!((gravity.rows(1, 1.0) - 1.0).abs() < 1e-9);
+        assert!((gravity.rows(2, 0.793) - 1.0).abs() < 1e-3);
+        assert!((1..30)
+            .all(|level| gravity.g(level) < gravity.g(level + 1) || gravity.g(level) == MAX_G));
+        assert_eq!(gravity.g(20), MAX_G);
+    }
+
+    #[test]
+    fn nes() {
+        let gravity = Gravity::Nes;
+        assert!((gravity.rows(1, 48.0 / NES_FPS) - 1.0).abs() < 1e-9);
+        assert_eq!(gravity.g(30), gravity.g(100));
+    }
+
+    #[test]
+    fn custom() {
+        let gravity: Gravity = "custom:0.5,1,20".parse().unwrap();
+        assert_eq!(gravity, Gravity::Custom(vec![0.5, 1.0, 20.0]));
+        assert_eq!(gravity.g(1), 0.5);
+        assert_eq!(gravity.g(3), MAX_G);
+        assert_eq!(gravity.g(10), MAX_G);
+        assert!("custom:".parse::<Gravity>().is_err());
+        assert!("custom:1,-2".parse::<Gravity>().is_err());
+        assert!("fast".parse::<Gravity>().is_err());
+    }
+}
@@ -2,35 +2,104 @@ mod ai;
 mod controls;
 mod events;
 mod game;
+mod gravity;
+mod modes;
+mod pieces;
+mod randomizer;
+mod records;
+mod scoring;
 mod tetromino;
 
 use controls::{Action, GameController};
 use events::Event;
+use game::Config;
+use modes::Mode;
+use pieces::PieceSet;
+use records::Records;
 use std::error::Error;
 use std::time::Duration;
 
 use termion::event::Key;
-use tetromino::Direction;
+use tetromino::{Direction, Rotation};
+
+const MAX_PREVIEW: usize = 6;
+const MAX_LEVEL: u32 = 30;
 
 fn main() -> Result<(), Box<dyn Error>> {
     pretty_env_logger::init();
-    match std::env::args().nth(1) {
-        None => play(),
-        Some(cmd) => {
-            if cmd == "evolve" {
-                evolve();
-                Ok(())
-            } else if cmd == "bot" {
-                bot()
-            } else {
-                Err("unknown command".into())
+    let mut args = std::env::args().skip(1);
+    let mut command = None;
+    let mut config = Config::default();
+    let mut ghost = true;
+    while let Some(arg) = args.next() {
+        if arg == "--mode" {
+            config.mode = args.next().ok_or("missing mode")?.parse()?;
+        } else if arg == "--level" {
+            config.level = args.next().ok_or("missing level")?.parse()?;
+            if !(1..=MAX_LEVEL).contains(&config.level) {
+                return Err(format!("level must be between 1 and {MAX_LEVEL}").into());
+            }
+        } else if arg == "--width" {
+            config.width = args.next().ok_or("missing width")?.parse()?;
+        } else if arg == "--height" {
+            config.height = args.next().ok_or("missing height")?.parse()?;
+            if config.height == 0 {
+                return Err("board must be at least 1 row tall".into());
+            }
+        } else if arg == "--pieces" {
+            let name = args.next().ok_or("missing piece set")?;
+            config.pieces = match name.parse() {
+                Ok(pieces) => pieces,
+                Err(_) => PieceSet::parse(&std::fs::read_to_string(&name)?)?,
+            };
+        } else if arg == "--no-ghost" {
+            ghost = false;
+        } else if arg == "--randomizer" {
+            config.randomizer = args.next().ok_or("missing randomizer")?.parse()?;
+        } else if arg == "--gravity" {
+            config.gravity = args.next().ok_or("missing gravity")?.parse()?;
+        } else if arg == "--lock-delay" {
+            let millis = args.next().ok_or("missing lock delay")?.parse()?;
+            config.lock_delay = Duration::from_millis(millis);
+        } else if arg == "--lock-reset" {
+            config.lock_reset = args.next().ok_or("missing lock reset")?.parse()?;
+        } else if arg == "--partial-lock-out" {
+            config.partial_lock_out = true;
+        } else if arg == "--preview" {
+            config.preview = args.next().ok_or("missing preview length")?.parse()?;
+            if config.preview > MAX_PREVIEW {
+                return Err(format!("preview can show at most {MAX_PREVIEW} pieces").into());
             }
+        } else if command.is_none() {
+            command = Some(arg);
+        } else {
+            return Err(format!("unexpected argument: {arg}").into());
+        }
+    }
+    // every piece has to be able to spawn
+    let widest = config.pieces.size().1;
+    if config.width < widest {
+        return Err(format!("board must be at least {widest} columns wide").into());
+    }
+    if let Mode::Cheese { rows, .. } = config.mode {
+        if rows as usize >= config.height {
+            return Err("cheese must leave at least one row free".into());
+        }
+    }
+    match command.as_deref() {
+        None => play(config, ghost),
+        Some("evolve") => {
+            evolve(config);
+            Ok(())
         }
+        Some("bot") => bot(config, ghost),
+        Some(_) => Err("unknown command".into()),
     }
 }
 
-fn bot() -> Result<(), Box<dyn Error>> {
-    let mut controller = GameController::new()?;
+fn bot(config: Config, ghost: bool) -> Result<(), Box<dyn Error>> {
+    let mut controller = GameController::new(config.clone())?;
+    controller.ghost = ghost;
     let event = events::receiver();
     let bot = ai::Population::single(
         ai::DNA(vec![
@@ -42,44 +111,53 @@ fn bot() -> Result<(), Box<dyn Error>> {
         ]),
         vec![
             Box::new(ai::genes::TotalHeight),
-            // Box::new(ai::genes::MaxHeight),
+            Box::new(ai::genes::MaxHeight),
             Box::new(ai::genes::LinesCleared),
             Box::new(ai::genes::Holes),
             Box::new(ai::genes::Bumpiness),
         ],
+        config,
     );
-    while !controller.game.over {
+    while !controller.game.over() {
         while let Ok(event) = event.try_recv() {
             if let Event::Input(Key::Ctrl('c')) = event {
                 return Ok(());
             }
         }
-        let (shifts, rotations) = bot.best_actions(0, &controller.game);
-        for _ in 0..10 {
-            controller.game.shift(Direction::Left);
+        // moves go through the controller like keys do, so the game clock
+        // keeps up with real time and timed modes end
+        controller.send(Action::Tick);
+        if controller.game.over() {
+            break;
+        }
+        let placement = bot.best_actions(0, &controller.game);
+        if placement.hold {
+            controller.send(Action::Hold);
+            controller.render()?;
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        for _ in 0..controller.game.width() {
+            controller.send(Action::Shift(Direction::Left));
             controller.render()?;
         }
-        for _ in 0..shifts {
-            controller.game.shift(Direction::Right);
+        for _ in 0..placement.shifts {
+            controller.send(Action::Shift(Direction::Right));
             controller.render()?;
             std::thread::sleep(Duration::from_millis(100));
         }
-        for _ in 0..rotations {
-            controller.game.rotate();
+        if let Some(rotation) = placement.rotation {
+            controller.send(Action::Turn(rotation));
             controller.render()?;
             std::thread::sleep(Duration::from_millis(100));
         }
-        controller.game.hard_drop();
-        controller.render()?;
-        std::thread::sleep(Duration::from_millis(100));
-        controller.game.tick();
+        controller.send(Action::HardDrop);
         controller.render()?;
         std::thread::sleep(Duration::from_millis(100));
     }
     Ok(())
 }
 
-fn evolve() {
+fn evolve(config: Config) {
     let mut population = ai::Population::new(
         1000,
         vec![
@@ -88,31 +166,69 @@ fn evolve() {
             Box::new(ai::genes::LinesCleared),
             Box::new(ai::genes::Holes),
             Box::new(ai::genes::Bumpiness),
+            Box::new(ai::genes::Combo),
+            Box::new(ai::genes::BackToBack),
         ],
         18,
+        config,
     );
 
     population.evolve(100);
 }
 
 // TODO: use anyhow for errors
-fn play() -> Result<(), Box<dyn Error>> {
-    let mut controller = GameController::new()?;
+fn play(config: Config, ghost: bool) -> Result<(), Box<dyn Error>> {
+    let standard = config.standard();
+    let mut controller = GameController::new(config)?;
+    controller.ghost = ghost;
     let event = events::receiver();
-    while !controller.game.over {
+    while !controller.game.over() {
         match event.recv()? {
             Event::Tick => controller.send(Action::Tick),
             Event::Input(key) => match key {
                 Key::Char('a') | Key::Left => controller.send(Action::Shift(Direction::Left)),
                 Key::Char('d') | Key::Right => controller.send(Action::Shift(Direction::Right)),
-                Key::Char('w') | Key::Up => controller.send(Action::Turn),
-                Key::Char('s') | Key::Down => controller.send(Action::HardDrop),
+                Key::Char('w') | Key::Char('x') | Key::Up => {
+                    controller.send(Action::Turn(Rotation::Clockwise))
+                }
+                Key::Char('z') => controller.send(Action::Turn(Rotation::CounterClockwise)),
+                Key::Char('e') => controller.send(Action::Turn(Rotation::Half)),
+                Key::Char('c') => controller.send(Action::Hold),
+                Key::Char('g') => controller.toggle_ghost(),
+                Key::Char('s') | Key::Down => controller.send(Action::SoftDrop),
+                Key::Char(' ') => controller.send(Action::HardDrop),
                 Key::Char('q') | Key::Ctrl('c') => break,
-                Key::Char(' ') => controller.toggle_pause(),
+                Key::Char('p') => controller.toggle_pause(),
                 _ => (),
             },
         }
         controller.render()?;
     }
+    let game = controller.game.clone();
+    drop(controller);
+    let score = &game.score;
+    println!(
+        "Level: {} Lines: {} Score: {} Perfect clears: {}",
+        score.level, score.lines, score.points, score.perfect_clears
+    );
+    let mode = game.mode();
+    for line in mode.results(&game) {
+        println!("{line}");
+    }
+    match mode.record(&game) {
+        Some(_) if !standard => println!("Personal bests are only kept with the standard rules"),
+        Some(record) => {
+            let mut records = Records::load();
+            let key = mode.to_string();
+            let best = records.get(&key);
+            if records.submit(&key, record, mode.lower_is_better()) {
+                println!("New personal best!");
+                records.save()?;
+            } else if let Some(best) = best {
+                println!("Personal best: {}", mode.show_record(best));
+            }
+        }
+        None => (),
+    }
     Ok(())
 }
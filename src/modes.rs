@@ -0,0 +1,422 @@
+use crate::game::Game;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Characters a status line may take, which keeps the standard board and
+/// its status within 80 columns.
+pub const STATUS_WIDTH: usize = 24;
+/// Lines a sprint clears unless told otherwise.
+const SPRINT_LINES: u32 = 40;
+/// Lines that win a marathon unless told otherwise.
+const MARATHON_LINES: u32 = 150;
+/// Time an ultra lasts unless told otherwise.
+const ULTRA_TIME: Duration = Duration::from_secs(120);
+/// Garbage rows a cheese race starts with unless told otherwise.
+const CHEESE_ROWS: u32 = 10;
+/// Chance that the hole of a cheese row is not under the one above it.
+const CHEESE_MESSINESS: f64 = 1.0;
+/// Wait for the first garbage row in survival.
+const SURVIVAL_DELAY: Duration = Duration::from_secs(5);
+/// Each survival garbage row comes this much sooner than the one before...
+const SURVIVAL_SPEEDUP: f64 = 0.95;
+/// ...but never sooner than this.
+const SURVIVAL_MIN_DELAY: Duration = Duration::from_secs(1);
+
+/// What a game is played for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Plays on until the stack tops out, a marathon without an end.
+    #[default]
+    Endless,
+    /// Wins once `lines` lines are cleared, going up a level every ten.
+    Marathon { lines: u32 },
+    /// Clears `lines` lines as fast as possible.
+    Sprint { lines: u32 },
+    /// Scores as many points as possible before `limit` runs out.
+    Ultra { limit: Duration },
+    /// Digs through `rows` rows of garbage, each with a hole somewhere else.
+    /// When `infinite`, new garbage keeps rising to keep `rows` rows of it.
+    Cheese { rows: u32, infinite: bool },
+    /// Lasts as long as possible while garbage rises faster and faster.
+    Survival,
+}
+
+impl Mode {
+    /// Sets up the board before the first piece spawns.
+    pub fn start(&self, game: &mut Game) {
+        if let Self::Cheese { rows, .. } = *self {
+            game.add_garbage(rows as usize, None, CHEESE_MESSINESS);
+        }
+    }
+
+    /// Runs after every lock, once its lines are cleared and the next piece
+    /// has spawned.
+    pub fn locked(&self, game: &mut Game) {
+        if let Self::Cheese {
+            rows,
+            infinite: true,
+        } = *self
+        {
+            let missing = rows.saturating_sub(game.garbage_left());
+            if missing > 0 {
+                game.add_garbage(missing as usize, None, CHEESE_MESSINESS);
+            }
+        }
+    }
+
+    /// Runs every time the game clock moves forward.
+    pub fn advanced(&self, game: &mut Game) {
+        if let Self::Survival = *self {
+            while !game.over() && game.time >= survival_due(game.garbage_added()) {
+                game.add_garbage(1, None, 0.0);
+            }
+        }
+    }
+
+    /// Whether the mode runs on the game clock, so a simulated game has to
+    /// move it along.
+    pub fn timed(&self) -> bool {
+        matches!(self, Self::Ultra { .. } | Self::Survival)
+    }
+
+    /// Whether the goal of the mode has been reached.
+    pub fn finished(&self, game: &Game) -> bool {
+        match *self {
+            Self::Endless | Self::Survival => false,
+            Self::Sprint { lines } | Self::Marathon { lines } => game.score.lines >= lines,
+            Self::Ultra { limit } => game.time >= limit,
+            Self::Cheese { infinite, .. } => !infinite && game.garbage_left() == 0,
+        }
+    }
+
+    /// How long the game may last, if the mode says.
+    pub fn time_limit(&self) -> Option<Duration> {
+        match *self {
+            Self::Ultra { limit } => Some(limit),
+            _ => None,
+        }
+    }
+
+    /// Progress shown right of the preview, one entry per line of at most
+    /// `STATUS_WIDTH` characters.
+    pub fn status(&self, game: &Game) -> Vec<String> {
+        match *self {
+            Self::Endless => vec![],
+            Self::Survival => vec![
+                format!("Time {}", clock(game.time)),
+                format!(
+                    "Next row {}",
+                    clock(survival_due(game.garbage_added()).saturating_sub(game.time))
+                ),
+            ],
+            Self::Sprint { lines } | Self::Marathon { lines } => vec![
+                format!("Time {}", clock(game.time)),
+                format!("Lines {}/{}", game.score.lines.min(lines), lines),
+            ],
+            Self::Ultra { limit } => vec![format!(
+                "Time left {}",
+                clock(limit.saturating_sub(game.time))
+            )],
+            Self::Cheese { infinite: true, .. } => {
+                vec![format!("Garbage cleared {}", game.garbage_cleared())]
+            }
+            Self::Cheese { .. } => vec![
+                format!("Time {}", clock(game.time)),
+                format!("Garbage left {}", game.garbage_left()),
+                format!("Pieces {}", game.score.pieces),
+            ],
+        }
+    }
+
+    /// Summary of a game that has ended, one entry per line.
+    pub fn results(&self, game: &Game) -> Vec<String> {
+        let score = &game.score;
+        let clears = &score.line_clears;
+        let breakdown = format!(
+            "Singles: {} Doubles: {} Triples: {} Tetrises: {} T-spins: {}",
+            clears[0], clears[1], clears[2], clears[3], score.t_spins
+        );
+        match *self {
+            Self::Endless => vec![
+                format!("Cleared {} lines in {}", score.lines, clock(game.time)),
+                breakdown,
+            ],
+            Self::Survival => vec![format!(
+                "Survived {} against {} garbage rows",
+                clock(game.time),
+                game.garbage_added()
+            )],
+            Self::Marathon { lines } => vec![
+                if self.finished(game) {
+                    format!("Marathon {lines} lines in {}", clock(game.time))
+                } else {
+                    format!(
+                        "Topped out after {} of {lines} lines in {}",
+                        score.lines,
+                        clock(game.time)
+                    )
+                },
+                breakdown,
+            ],
+            Self::Sprint { lines } if self.finished(game) => {
+                let seconds = game.time.as_secs_f64();
+                let rate = if seconds > 0.0 {
+                    score.pieces as f64 / seconds
+                } else {
+                    0.0
+                };
+                vec![
+                    format!("Sprint {lines} lines in {}", clock(game.time)),
+                    format!("Pieces: {} ({rate:.2} per second)", score.pieces),
+                ]
+            }
+            Self::Sprint { lines } => {
+                vec![format!("Topped out after {} of {lines} lines", score.lines)]
+            }
+            Self::Ultra { limit } if self.finished(game) => vec![
+                format!("Ultra {}: {} points", clock(limit), score.points),
+                format!("Lines: {} Pieces: {}", score.lines, score.pieces),
+            ],
+            Self::Ultra { limit } => vec![format!(
+                "Topped out with {} left",
+                clock(limit.saturating_sub(game.time))
+            )],
+            Self::Cheese { infinite: true, .. } => vec![format!(
+                "Cleared {} garbage rows in {} with {} pieces",
+                game.garbage_cleared(),
+                clock(game.time),
+                score.pieces
+            )],
+            Self::Cheese { rows, .. } if self.finished(game) => vec![format!(
+                "Dug through {rows} rows in {} with {} pieces",
+                clock(game.time),
+                score.pieces
+            )],
+            Self::Cheese { rows, .. } => vec![format!(
+                "Topped out with {} of {rows} garbage rows left",
+                game.garbage_left()
+            )],
+        }
+    }
+
+    /// What a finished game puts on the record, if the mode keeps one.
+    pub fn record(&self, game: &Game) -> Option<u64> {
+        match *self {
+            Self::Sprint { .. } if self.finished(game) => Some(game.time.as_millis() as u64),
+            Self::Ultra { .. } if self.finished(game) => Some(game.score.points as u64),
+            Self::Cheese {
+                infinite: false, ..
+            } if self.finished(game) => Some(game.score.pieces as u64),
+            Self::Survival if game.top_out.is_some() => Some(game.time.as_millis() as u64),
+            _ => None,
+        }
+    }
+
+    /// Whether a lower record is a better one.
+    pub fn lower_is_better(&self) -> bool {
+        matches!(self, Self::Sprint { .. } | Self::Cheese { .. })
+    }
+
+    /// Shows a record of this mode the way `record` made it.
+    pub fn show_record(&self, record: u64) -> String {
+        match *self {
+            Self::Sprint { .. } | Self::Survival => clock(Duration::from_millis(record)),
+            _ => record.to_string(),
+        }
+    }
+}
+
+/// Game time at which survival garbage row `row` rises, counting from 0.
+fn survival_due(row: u32) -> Duration {
+    (0..=row)
+        .map(|row| {
+            SURVIVAL_DELAY
+                .mul_f64(SURVIVAL_SPEEDUP.powi(row as i32))
+                .max(SURVIVAL_MIN_DELAY)
+        })
+        .sum()
+}
+
+/// Formats game time as minutes, seconds and milliseconds, like `1:23.456`.
+pub fn clock(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Parses a positive number given after a mode name, such as the 20 in `sprint:20`.
+fn target(name: &str, value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!("invalid {name} target: {value}")),
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let (mode, value) = match name.split_once(':') {
+            Some((mode, value)) => (mode, Some(value)),
+            None => (name, None),
+        };
+        match (mode, value) {
+            ("endless", None) => Ok(Self::Endless),
+            ("survival", None) => Ok(Self::Survival),
+            ("sprint", None) => Ok(Self::Sprint {
+                lines: SPRINT_LINES,
+            }),
+            ("sprint", Some(lines)) => Ok(Self::Sprint {
+                lines: target(mode, lines)?,
+            }),
+            ("marathon", None) => Ok(Self::Marathon {
+                lines: MARATHON_LINES,
+            }),
+            ("marathon", Some(lines)) => Ok(Self::Marathon {
+                lines: target(mode, lines)?,
+            }),
+            ("ultra", None) => Ok(Self::Ultra { limit: ULTRA_TIME }),
+            ("ultra", Some(seconds)) => Ok(Self::Ultra {
+                limit: Duration::from_secs(target(mode, seconds)?.into()),
+            }),
+            ("cheese" | "infinite-cheese", _) => Ok(Self::Cheese {
+                rows: match value {
+                    Some(rows) => target(mode, rows)?,
+                    None => CHEESE_ROWS,
+                },
+                infinite: mode == "infinite-cheese",
+            }),
+            _ => Err(format!("unknown mode: {name}")),
+        }
+    }
+}
+
+/// Names the mode the way `FromStr` reads it, which also keys its records.
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Endless => write!(f, "endless"),
+            Self::Survival => write!(f, "survival"),
+            Self::Marathon { lines } => write!(f, "marathon:{lines}"),
+            Self::Sprint { lines } => write!(f, "sprint:{lines}"),
+            Self::Ultra { limit } => write!(f, "ultra:{}", limit.as_secs()),
+            Self::Cheese { rows, infinite } => {
+                let name = if *infinite {
+                    "infinite-cheese"
+                } else {
+                    "cheese"
+                };
+                write!(f, "{name}:{rows}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Config;
+
+    #[test]
+    fn parse_modes() {
+        assert_eq!("endless".parse(), Ok(Mode::Endless));
+        assert_eq!("sprint".parse(), Ok(Mode::Sprint { lines: 40 }));
+        assert_eq!("sprint:20".parse(), Ok(Mode::Sprint { lines: 20 }));
+        assert!("sprint:0".parse::<Mode>().is_err());
+        assert_eq!("marathon".parse(), Ok(Mode::Marathon { lines: 150 }));
+        assert!("sprint:".parse::<Mode>().is_err());
+        let limit = Duration::from_secs(120);
+        assert_eq!("ultra".parse(), Ok(Mode::Ultra { limit }));
+        let limit = Duration::from_secs(180);
+        assert_eq!("ultra:180".parse(), Ok(Mode::Ultra { limit }));
+        assert!("ultra:-1".parse::<Mode>().is_err());
+        let cheese = |rows, infinite| Ok(Mode::Cheese { rows, infinite });
+        assert_eq!("cheese".parse(), cheese(10, false));
+        assert_eq!("infinite-cheese:5".parse(), cheese(5, true));
+        assert!("endless:5".parse::<Mode>().is_err());
+        assert!("zen".parse::<Mode>().is_err());
+        for mode in [
+            "endless",
+            "marathon:200",
+            "sprint:100",
+            "ultra:60",
+            "cheese:18",
+            "infinite-cheese:9",
+            "survival",
+        ] {
+            assert_eq!(mode.parse::<Mode>().unwrap().to_string(), mode);
+        }
+    }
+
+    #[test]
+    fn survival_speeds_up() {
+        assert_eq!(survival_due(0), Duration::from_secs(5));
+        assert_eq!(survival_due(1), Duration::from_millis(9750));
+        let delays = (1..100)
+            .map(|row| survival_due(row) - survival_due(row - 1))
+            .collect::<Vec<_>>();
+        assert!(delays.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(delays[98], Duration::from_secs(1));
+    }
+
+    /// Status of `mode` with the widest numbers a long game could reach.
+    fn widest_status(mode: &str) -> Vec<String> {
+        let config = Config {
+            mode: mode.parse().unwrap(),
+            ..Config::default()
+        };
+        let mut game = Game::with_config(1, config);
+        game.time = Duration::from_millis(59 * 60_000 + 59_999);
+        game.score.lines = 999;
+        game.score.pieces = 9999;
+        game.mode().status(&game)
+    }
+
+    #[test]
+    fn status_fits() {
+        let modes = [
+            "endless",
+            "marathon:999",
+            "sprint:999",
+            "ultra:3599",
+            "cheese:99",
+            "infinite-cheese:99",
+            "survival",
+        ];
+        for mode in modes {
+            for line in widest_status(mode) {
+                assert!(line.len() <= STATUS_WIDTH, "{mode}: {line}");
+            }
+        }
+        assert_eq!(widest_status("sprint"), ["Time 59:59.999", "Lines 40/40"]);
+        assert_eq!(widest_status("ultra:3600"), ["Time left 0:00.001"]);
+    }
+
+    #[test]
+    fn endless_results() {
+        let mut game = Game::with_config(1, Config::default());
+        game.time = Duration::from_millis(83_456);
+        game.score.lines = 12;
+        game.score.line_clears = [3, 1, 1, 1];
+        game.score.t_spins = 1;
+        assert_eq!(
+            game.mode().results(&game),
+            [
+                "Cleared 12 lines in 1:23.456",
+                "Singles: 3 Doubles: 1 Triples: 1 Tetrises: 1 T-spins: 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn clock_time() {
+        assert_eq!(clock(Duration::ZERO), "0:00.000");
+        assert_eq!(clock(Duration::from_millis(83_456)), "1:23.456");
+        assert_eq!(clock(Duration::from_secs(600)), "10:00.000");
+    }
+}
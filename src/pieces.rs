@@ -0,0 +1,520 @@
+use crate::tetromino::{Color, Kicks, PieceKind};
+use std::str::FromStr;
+
+/// The kinds of pieces a game deals from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PieceSet(pub Vec<&'static PieceKind>);
+
+impl PieceSet {
+    pub fn tetrominos() -> Self {
+        Self(PieceKind::ALL.to_vec())
+    }
+
+    pub fn pentominos() -> Self {
+        Self(PENTOMINOS.to_vec())
+    }
+
+    /// The monomino and both trominos.
+    pub fn small() -> Self {
+        Self(vec![I1, I3, L3])
+    }
+
+    /// Tetrominos and pentominos mixed together.
+    pub fn big() -> Self {
+        Self([&PieceKind::ALL[..], &PENTOMINOS[..]].concat())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Rows and columns it takes to show any piece of the set in its spawn
+    /// orientation.
+    pub fn size(&self) -> (usize, usize) {
+        let span = |values: &mut dyn Iterator<Item = isize>| {
+            let (min, max) = values.fold((isize::MAX, isize::MIN), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+            (max - min + 1) as usize
+        };
+        self.0.iter().fold((0, 0), |(rows, columns), kind| {
+            let spawn = kind.cells[0];
+            (
+                rows.max(span(&mut spawn.iter().map(|cell| cell.0))),
+                columns.max(span(&mut spawn.iter().map(|cell| cell.1))),
+            )
+        })
+    }
+
+    /// Reads a set of custom pieces. Every piece starts with a header line
+    /// such as `piece T magenta srs spin`, naming its color, its kicks
+    /// (`srs`, `i` or `none`) and whether it can T-spin, which takes a 3x3
+    /// box. Its four
+    /// orientations follow as rows of `#` and `.`, separated by `-` lines.
+    /// Lines starting with `//` are comments.
+    ///
+    /// The pieces are leaked, as they live for as long as the program.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut pieces = vec![];
+        let mut piece: Option<ParsedPiece> = None;
+        let mut row = 0;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix("piece ") {
+                if let Some(piece) = piece.take() {
+                    pieces.push(piece.leak()?);
+                }
+                piece = Some(header.parse()?);
+                row = 0;
+                continue;
+            }
+            let piece = piece
+                .as_mut()
+                .ok_or_else(|| format!("cells before the first piece: {line}"))?;
+            if line == "-" {
+                piece.orientations.push(vec![]);
+                row = 0;
+                continue;
+            }
+            let cells = piece.orientations.last_mut().unwrap();
+            for (column, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells.push((row, column as isize)),
+                    '.' => (),
+                    _ => return Err(format!("unexpected {c:?} in piece {}", piece.name)),
+                }
+            }
+            row += 1;
+        }
+        if let Some(piece) = piece {
+            pieces.push(piece.leak()?);
+        }
+        if pieces.is_empty() {
+            return Err("no pieces defined".to_string());
+        }
+        for (i, piece) in pieces.iter().enumerate() {
+            if pieces[..i].iter().any(|other| other.name == piece.name) {
+                return Err(format!("piece {} defined twice", piece.name));
+            }
+        }
+        Ok(Self(pieces))
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self::tetrominos()
+    }
+}
+
+impl FromStr for PieceSet {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "tetrominos" => Ok(Self::tetrominos()),
+            "pentominos" => Ok(Self::pentominos()),
+            "small" => Ok(Self::small()),
+            "big" => Ok(Self::big()),
+            _ => Err(format!("unknown piece set: {name}")),
+        }
+    }
+}
+
+/// A custom piece while its orientations are being read.
+struct ParsedPiece {
+    name: String,
+    color: Color,
+    kicks: Kicks,
+    spin: bool,
+    orientations: Vec<Vec<(isize, isize)>>,
+}
+
+impl ParsedPiece {
+    /// Checks the piece is complete and gives it a `'static` lifetime.
+    fn leak(self) -> Result<&'static PieceKind, String> {
+        let name = self.name;
+        if self.orientations.len() != 4 {
+            return Err(format!(
+                "piece {name} needs 4 orientations, not {}",
+                self.orientations.len()
+            ));
+        }
+        let size = self.orientations[0].len();
+        if size == 0 || self.orientations.iter().any(|cells| cells.len() != size) {
+            return Err(format!(
+                "every orientation of piece {name} needs the same number of cells"
+            ));
+        }
+        // T-spins are judged by the corners around the middle of a 3x3 box
+        let extent = self
+            .orientations
+            .iter()
+            .flatten()
+            .fold((0, 0), |(rows, cols), cell| {
+                (rows.max(cell.0 + 1), cols.max(cell.1 + 1))
+            });
+        if self.spin && extent != (3, 3) {
+            return Err(format!("piece {name} needs a 3x3 box to spin"));
+        }
+        let mut cells = self
+            .orientations
+            .into_iter()
+            .map(|cells| &*Box::leak(cells.into_boxed_slice()));
+        let cells = [(); 4].map(|_| cells.next().unwrap());
+        Ok(Box::leak(Box::new(PieceKind {
+            name: Box::leak(name.into_boxed_str()),
+            color: self.color,
+            cells,
+            kicks: self.kicks,
+            spin: self.spin,
+        })))
+    }
+}
+
+/// Reads the `piece` line of a custom piece.
+impl FromStr for ParsedPiece {
+    type Err = String;
+
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let mut words = header.split_whitespace();
+        let name = words.next().ok_or("piece without a name")?.to_string();
+        let color = match words.next() {
+            Some("red") => Color::Red,
+            Some("green") => Color::Green,
+            Some("blue") => Color::Blue,
+            Some("magenta") => Color::Magenta,
+            Some("yellow") => Color::Yellow,
+            Some("cyan") => Color::Cyan,
+            Some("orange") => Color::Orange,
+            Some(color) => return Err(format!("unknown color: {color}")),
+            None => return Err(format!("piece {name} has no color")),
+        };
+        let mut piece = ParsedPiece {
+            name,
+            color,
+            kicks: Kicks::Srs,
+            spin: false,
+            orientations: vec![vec![]],
+        };
+        for word in words {
+            match word {
+                "srs" => piece.kicks = Kicks::Srs,
+                "i" => piece.kicks = Kicks::I,
+                "none" => piece.kicks = Kicks::None,
+                "spin" => piece.spin = true,
+                _ => return Err(format!("unknown option for piece {}: {word}", piece.name)),
+            }
+        }
+        Ok(piece)
+    }
+}
+
+const PENTOMINOS: [&PieceKind; 12] = [F5, I5, L5, N5, P5, T5, U5, V5, W5, X5, Y5, Z5];
+
+const F5: &PieceKind = &PieceKind {
+    name: "F5",
+    color: Color::Red,
+    cells: [
+        &[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)],
+        &[(0, 1), (1, 0), (1, 1), (1, 2), (2, 2)],
+        &[(0, 1), (1, 1), (1, 2), (2, 0), (2, 1)],
+        &[(0, 0), (1, 0), (1, 1), (1, 2), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const I5: &PieceKind = &PieceKind {
+    name: "I5",
+    color: Color::Cyan,
+    cells: [
+        &[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)],
+        &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2)],
+        &[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)],
+        &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2)],
+    ],
+    kicks: Kicks::I,
+    spin: false,
+};
+
+const L5: &PieceKind = &PieceKind {
+    name: "L5",
+    color: Color::Orange,
+    cells: [
+        &[(1, 3), (2, 0), (2, 1), (2, 2), (2, 3)],
+        &[(0, 1), (1, 1), (2, 1), (3, 1), (3, 2)],
+        &[(1, 0), (1, 1), (1, 2), (1, 3), (2, 0)],
+        &[(0, 1), (0, 2), (1, 2), (2, 2), (3, 2)],
+    ],
+    kicks: Kicks::I,
+    spin: false,
+};
+
+const N5: &PieceKind = &PieceKind {
+    name: "N5",
+    color: Color::Green,
+    cells: [
+        &[(1, 2), (1, 3), (2, 0), (2, 1), (2, 2)],
+        &[(0, 1), (1, 1), (2, 1), (2, 2), (3, 2)],
+        &[(1, 1), (1, 2), (1, 3), (2, 0), (2, 1)],
+        &[(0, 1), (1, 1), (1, 2), (2, 2), (3, 2)],
+    ],
+    kicks: Kicks::I,
+    spin: false,
+};
+
+const P5: &PieceKind = &PieceKind {
+    name: "P5",
+    color: Color::Yellow,
+    cells: [
+        &[(0, 0), (0, 1), (1, 0), (1, 1), (1, 2)],
+        &[(0, 1), (0, 2), (1, 1), (1, 2), (2, 1)],
+        &[(1, 0), (1, 1), (1, 2), (2, 1), (2, 2)],
+        &[(0, 1), (1, 0), (1, 1), (2, 0), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const T5: &PieceKind = &PieceKind {
+    name: "T5",
+    color: Color::Magenta,
+    cells: [
+        &[(0, 1), (1, 1), (2, 0), (2, 1), (2, 2)],
+        &[(0, 0), (1, 0), (1, 1), (1, 2), (2, 0)],
+        &[(0, 0), (0, 1), (0, 2), (1, 1), (2, 1)],
+        &[(0, 2), (1, 0), (1, 1), (1, 2), (2, 2)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const U5: &PieceKind = &PieceKind {
+    name: "U5",
+    color: Color::Blue,
+    cells: [
+        &[(0, 0), (0, 2), (1, 0), (1, 1), (1, 2)],
+        &[(0, 1), (0, 2), (1, 1), (2, 1), (2, 2)],
+        &[(1, 0), (1, 1), (1, 2), (2, 0), (2, 2)],
+        &[(0, 0), (0, 1), (1, 1), (2, 0), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const V5: &PieceKind = &PieceKind {
+    name: "V5",
+    color: Color::Cyan,
+    cells: [
+        &[(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)],
+        &[(0, 0), (0, 1), (0, 2), (1, 0), (2, 0)],
+        &[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)],
+        &[(0, 2), (1, 2), (2, 0), (2, 1), (2, 2)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const W5: &PieceKind = &PieceKind {
+    name: "W5",
+    color: Color::Green,
+    cells: [
+        &[(0, 0), (1, 0), (1, 1), (2, 1), (2, 2)],
+        &[(0, 1), (0, 2), (1, 0), (1, 1), (2, 0)],
+        &[(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)],
+        &[(0, 2), (1, 1), (1, 2), (2, 0), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const X5: &PieceKind = &PieceKind {
+    name: "X5",
+    color: Color::Red,
+    cells: [
+        &[(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)],
+        &[(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)],
+        &[(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)],
+        &[(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const Y5: &PieceKind = &PieceKind {
+    name: "Y5",
+    color: Color::Magenta,
+    cells: [
+        &[(1, 2), (2, 0), (2, 1), (2, 2), (2, 3)],
+        &[(0, 1), (1, 1), (2, 1), (2, 2), (3, 1)],
+        &[(1, 0), (1, 1), (1, 2), (1, 3), (2, 1)],
+        &[(0, 2), (1, 1), (1, 2), (2, 2), (3, 2)],
+    ],
+    kicks: Kicks::I,
+    spin: false,
+};
+
+const Z5: &PieceKind = &PieceKind {
+    name: "Z5",
+    color: Color::Orange,
+    cells: [
+        &[(0, 0), (0, 1), (1, 1), (2, 1), (2, 2)],
+        &[(0, 2), (1, 0), (1, 1), (1, 2), (2, 0)],
+        &[(0, 0), (0, 1), (1, 1), (2, 1), (2, 2)],
+        &[(0, 2), (1, 0), (1, 1), (1, 2), (2, 0)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const I1: &PieceKind = &PieceKind {
+    name: "I1",
+    color: Color::Yellow,
+    cells: [&[(0, 0)], &[(0, 0)], &[(0, 0)], &[(0, 0)]],
+    kicks: Kicks::None,
+    spin: false,
+};
+
+const I3: &PieceKind = &PieceKind {
+    name: "I3",
+    color: Color::Cyan,
+    cells: [
+        &[(1, 0), (1, 1), (1, 2)],
+        &[(0, 1), (1, 1), (2, 1)],
+        &[(1, 0), (1, 1), (1, 2)],
+        &[(0, 1), (1, 1), (2, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+const L3: &PieceKind = &PieceKind {
+    name: "L3",
+    color: Color::Orange,
+    cells: [
+        &[(0, 0), (1, 0), (1, 1)],
+        &[(0, 0), (0, 1), (1, 0)],
+        &[(0, 0), (0, 1), (1, 1)],
+        &[(0, 1), (1, 0), (1, 1)],
+    ],
+    kicks: Kicks::Srs,
+    spin: false,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetromino::{Orientation, Rotation, Tetromino};
+
+    #[test]
+    fn built_in_sets() {
+        for name in ["tetrominos", "pentominos", "small", "big"] {
+            let set: PieceSet = name.parse().unwrap();
+            for kind in &set.0 {
+                let size = kind.cells[0].len();
+                assert!(kind.cells.iter().all(|cells| cells.len() == size));
+            }
+        }
+        assert_eq!(PieceSet::tetrominos().size(), (2, 4));
+        assert_eq!(PieceSet::pentominos().size(), (3, 5));
+        assert_eq!(PieceSet::small().size(), (2, 3));
+        assert_eq!(PieceSet::big().len(), 19);
+    }
+
+    #[test]
+    fn pentominos_turn_in_their_box() {
+        // turning clockwise moves (row, column) to (column, size - 1 - row)
+        for kind in PieceSet::small().0.into_iter().chain(PENTOMINOS) {
+            let box_size = kind
+                .cells
+                .iter()
+                .flat_map(|cells| cells.iter())
+                .map(|&(y, x)| y.max(x))
+                .max()
+                .unwrap()
+                + 1;
+            for (from, to) in kind.cells.iter().zip(kind.cells.iter().cycle().skip(1)) {
+                let mut turned = from
+                    .iter()
+                    .map(|&(y, x)| (x, box_size - 1 - y))
+                    .collect::<Vec<_>>();
+                turned.sort_unstable();
+                assert_eq!(turned, to.to_vec(), "{}", kind.name);
+            }
+        }
+    }
+
+    const CUSTOM: &str = "
+        // a domino and a tall corner
+        piece D red none
+        ##
+        -
+        #.
+        #.
+        -
+        ..
+        ##
+        -
+        .#
+        .#
+
+        piece C green srs
+        #.
+        ##
+        -
+        ##
+        #.
+        -
+        ##
+        .#
+        -
+        .#
+        ##
+    ";
+
+    #[test]
+    fn parse_custom() {
+        let set = PieceSet::parse(CUSTOM).unwrap();
+        assert_eq!(set.len(), 2);
+        let domino = set.0[0];
+        assert_eq!(
+            (domino.name, domino.color, domino.kicks),
+            ("D", Color::Red, Kicks::None)
+        );
+        assert_eq!(domino.cells[3], [(0, 1), (1, 1)]);
+        assert!(!set.0[1].spin);
+        let mut piece = Tetromino::new(set.0[1]);
+        assert!(piece.rotate(Rotation::Clockwise, |_| true).is_some());
+        assert_eq!(piece.orientation, Orientation::Right);
+        assert_eq!(piece.cells(), [(0, 0), (0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn example_file() {
+        let set = PieceSet::parse(include_str!("../pieces.txt")).unwrap();
+        assert_eq!(set.len(), 3);
+        // the same T as the built-in one, cells are just read in order
+        for (parsed, built_in) in set.0[0].cells.iter().zip(PieceKind::T.cells) {
+            let mut built_in = built_in.to_vec();
+            built_in.sort_unstable();
+            assert_eq!(parsed.to_vec(), built_in);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(PieceSet::parse("").is_err());
+        assert!(PieceSet::parse("#").is_err());
+        assert!(PieceSet::parse("piece A pink\n#\n-\n#\n-\n#\n-\n#").is_err());
+        assert!(PieceSet::parse("piece A red\n#\n-\n#\n-\n#").is_err());
+        assert!(PieceSet::parse("piece A red\n#\n-\n#\n-\n#\n-\n##").is_err());
+        assert!(PieceSet::parse("piece A red\n#\n-\n#\n-\n#\n-\n#").is_ok());
+        let spin = CUSTOM.replace("green srs", "green srs spin");
+        assert!(PieceSet::parse(&spin).is_err());
+        let twice = "piece A red\n#\n-\n#\n-\n#\n-\n#\npiece A blue\n#\n-\n#\n-\n#\n-\n#";
+        assert!(PieceSet::parse(twice).is_err());
+    }
+}
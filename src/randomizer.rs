@@ -0,0 +1,181 @@
+use crate::pieces::PieceSet;
+use rand::{prelude::SliceRandom, Rng};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+const HISTORY_SIZE: usize = 4;
+/// Pieces TGM fills its history with before the first deal.
+const TGM_HISTORY: [&str; HISTORY_SIZE] = ["Z", "S", "S", "Z"];
+
+/// Picks the index of the next piece out of `pieces` available ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Randomizer {
+    /// Every piece is equally likely, no matter what came before.
+    Uniform,
+    /// Shuffled bag with `copies` of every piece, refilled once empty.
+    Bag { copies: usize, bag: Vec<usize> },
+    /// TGM-style: draws up to `rolls` times in all, keeping the first piece
+    /// that isn't in recent history, or else the last one drawn.
+    History {
+        rolls: usize,
+        history: VecDeque<usize>,
+    },
+    /// NES-style: reroll once if the piece repeats the previous one.
+    Nes { last: Option<usize> },
+}
+
+impl Randomizer {
+    pub fn bag(copies: usize) -> Self {
+        Self::Bag {
+            copies,
+            bag: Vec::new(),
+        }
+    }
+
+    pub fn history(rolls: usize) -> Self {
+        Self::History {
+            rolls,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+        }
+    }
+
+    /// Gets ready to deal from `pieces`. A history randomizer starts out
+    /// with S and Z in its history, as in TGM, so the game doesn't open with
+    /// them.
+    pub fn prime(&mut self, pieces: &PieceSet) {
+        if let Self::History { history, .. } = self {
+            history.clear();
+            for name in TGM_HISTORY {
+                if let Some(index) = pieces.0.iter().position(|kind| kind.name == name) {
+                    history.push_back(index);
+                }
+            }
+        }
+    }
+
+    pub fn nes() -> Self {
+        Self::Nes { last: None }
+    }
+
+    pub fn next(&mut self, pieces: usize, rng: &mut impl Rng) -> usize {
+        match self {
+            Self::Uniform => rng.gen_range(0, pieces),
+            Self::Bag { copies, bag } => {
+                if bag.is_empty() {
+                    for _ in 0..*copies {
+                        bag.extend(0..pieces);
+                    }
+                    bag.shuffle(rng);
+                }
+                bag.pop().unwrap()
+            }
+            Self::History { rolls, history } => {
+                let mut piece = rng.gen_range(0, pieces);
+                for _ in 1..*rolls {
+                    if !history.contains(&piece) {
+                        break;
+                    }
+                    piece = rng.gen_range(0, pieces);
+                }
+                if history.len() == HISTORY_SIZE {
+                    history.pop_front();
+                }
+                history.push_back(piece);
+                piece
+            }
+            Self::Nes { last } => {
+                let mut piece = rng.gen_range(0, pieces + 1);
+                if piece == pieces || Some(piece) == *last {
+                    piece = rng.gen_range(0, pieces);
+                }
+                *last = Some(piece);
+                piece
+            }
+        }
+    }
+}
+
+impl Default for Randomizer {
+    fn default() -> Self {
+        Self::bag(1)
+    }
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "uniform" => Ok(Self::Uniform),
+            "7bag" => Ok(Self::bag(1)),
+            "14bag" => Ok(Self::bag(2)),
+            "tgm" => Ok(Self::history(6)),
+            "nes" => Ok(Self::nes()),
+            _ => Err(format!("unknown randomizer: {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::{SeedableRng, SmallRng};
+
+    fn sequence(mut randomizer: Randomizer, seed: u64, len: usize) -> Vec<usize> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        (0..len).map(|_| randomizer.next(7, &mut rng)).collect()
+    }
+
+    #[test]
+    fn bag_deals_every_piece() {
+        let pieces = sequence(Randomizer::bag(1), 42, 70);
+        for bag in pieces.chunks(7) {
+            let mut bag = bag.to_vec();
+            bag.sort_unstable();
+            assert_eq!(bag, (0..7).collect::<Vec<_>>());
+        }
+        let pieces = sequence(Randomizer::bag(2), 42, 70);
+        for bag in pieces.chunks(14) {
+            assert!((0..7).all(|p| bag.iter().filter(|&&x| x == p).count() == 2));
+        }
+    }
+
+    #[test]
+    fn tgm_history() {
+        let mut randomizer: Randomizer = "tgm".parse().unwrap();
+        randomizer.prime(&PieceSet::tetrominos());
+        let Randomizer::History { rolls, history } = &randomizer else {
+            unreachable!()
+        };
+        // Z, S, S, Z in the order of `PieceKind::ALL`
+        assert_eq!((*rolls, history), (6, &VecDeque::from([5, 4, 4, 5])));
+        // history only ever rejects what it holds, so the sets without an
+        // S or a Z start with an empty one
+        let mut randomizer: Randomizer = "tgm".parse().unwrap();
+        randomizer.prime(&PieceSet::pentominos());
+        assert!(matches!(randomizer, Randomizer::History { history, .. } if history.is_empty()));
+        // with every piece in history, all six draws are made
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut full = Randomizer::History {
+            rolls: 6,
+            history: VecDeque::from([0, 0, 0, 0]),
+        };
+        full.next(1, &mut rng);
+        let mut replay = SmallRng::seed_from_u64(1);
+        for _ in 0..6 {
+            replay.gen_range(0usize, 1);
+        }
+        assert_eq!(rng.gen::<u64>(), replay.gen::<u64>());
+    }
+
+    #[test]
+    fn reproducible() {
+        for name in &["uniform", "7bag", "14bag", "tgm", "nes"] {
+            let randomizer: Randomizer = name.parse().unwrap();
+            assert_eq!(
+                sequence(randomizer.clone(), 7, 100),
+                sequence(randomizer, 7, 100)
+            );
+        }
+    }
+}
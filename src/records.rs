@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+/// Personal bests kept between games, one `<key> <value>` line each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Records(BTreeMap<String, u64>);
+
+impl Records {
+    /// `$XDG_DATA_HOME/rusted-tetris/records`, or under `~/.local/share`
+    /// if that is not set.
+    fn path() -> Option<PathBuf> {
+        let data = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+        Some(data.join("rusted-tetris").join("records"))
+    }
+
+    /// Reads the records saved so far, starting afresh if there are none.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Reads records written by `Display`, skipping lines it can't make sense of.
+    fn parse(text: &str) -> Self {
+        let records = text
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                Some((key.to_string(), value.trim().parse().ok()?))
+            })
+            .collect();
+        Records(records)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.0.get(key).copied()
+    }
+
+    /// Keeps `value` as the record for `key` if there is none yet or it
+    /// beats the old one, lower or higher being better as `lower` says.
+    /// Returns whether it did.
+    pub fn submit(&mut self, key: &str, value: u64, lower: bool) -> bool {
+        let better = match self.get(key) {
+            Some(best) if lower => value < best,
+            Some(best) => value > best,
+            None => true,
+        };
+        if better {
+            self.0.insert(key.to_string(), value);
+        }
+        better
+    }
+}
+
+impl fmt::Display for Records {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.0 {
+            writeln!(f, "{key} {value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_best() {
+        let mut records = Records::default();
+        assert!(records.submit("sprint:40", 90_000, true));
+        assert!(!records.submit("sprint:40", 95_000, true));
+        assert!(records.submit("sprint:40", 85_000, true));
+        assert!(records.submit("sprint:20", 50_000, true));
+        assert!(records.submit("ultra", 1000, false));
+        assert!(!records.submit("ultra", 900, false));
+        assert_eq!(records.get("sprint:40"), Some(85_000));
+        assert_eq!(records.get("sprint:100"), None);
+        assert_eq!(Records::parse(&records.to_string()), records);
+    }
+
+    #[test]
+    fn skips_garbled_lines() {
+        let records = Records::parse("sprint:40 85000\nnonsense\nsprint:20 soon\n");
+        assert_eq!(records.0.len(), 1);
+        assert_eq!(records.get("sprint:40"), Some(85_000));
+    }
+}
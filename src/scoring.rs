@@ -0,0 +1,268 @@
+/// Points for clearing 0 to 4 rows at once, before the level multiplier.
+const LINE_POINTS: [u32; 5] = [0, 100, 300, 500, 800];
+/// Same as `LINE_POINTS`, but for T-spins clearing 0 to 3 rows.
+const T_SPIN_POINTS: [u32; 4] = [400, 800, 1200, 1600];
+/// Same as `LINE_POINTS`, but for T-spin Minis clearing 0 to 2 rows.
+const MINI_POINTS: [u32; 3] = [100, 200, 400];
+/// Bonus for leaving the board empty with 1 to 4 rows, on top of the clear itself.
+const PERFECT_CLEAR_POINTS: [u32; 5] = [0, 800, 1200, 1800, 2000];
+/// Replaces the tetris entry of `PERFECT_CLEAR_POINTS` when back-to-back.
+const BACK_TO_BACK_PERFECT_TETRIS: u32 = 3200;
+/// Bonus for every clear in a combo after the first one.
+const COMBO_POINTS: u32 = 50;
+const LINES_PER_LEVEL: u32 = 10;
+
+const ROW_NAMES: [&str; 5] = ["", "SINGLE", "DOUBLE", "TRIPLE", "TETRIS"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// What a single lock achieved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clear {
+    pub rows: u32,
+    pub spin: Spin,
+    /// Clears in a row before this one, as counted by `Score::combo`.
+    pub combo: u32,
+    /// Whether this difficult clear follows another one.
+    pub back_to_back: bool,
+    /// Whether the board was left completely empty.
+    pub perfect: bool,
+}
+
+impl Clear {
+    pub const NONE: Clear = Clear {
+        rows: 0,
+        spin: Spin::None,
+        combo: 0,
+        back_to_back: false,
+        perfect: false,
+    };
+
+    fn points(&self) -> u32 {
+        let rows = self.rows as usize;
+        match self.spin {
+            // pentominos can clear five rows, which scores as a tetris
+            Spin::None => LINE_POINTS[rows.min(LINE_POINTS.len() - 1)],
+            Spin::Mini => MINI_POINTS[rows.min(MINI_POINTS.len() - 1)],
+            Spin::Full => T_SPIN_POINTS[rows.min(T_SPIN_POINTS.len() - 1)],
+        }
+    }
+
+    /// Tetrises and spins that clear lines keep a back-to-back chain going.
+    pub fn difficult(&self) -> bool {
+        self.rows >= 4 || self.rows > 0 && self.spin != Spin::None
+    }
+
+    /// Callout for the renderer, such as "BACK-TO-BACK T-SPIN DOUBLE", if
+    /// the clear is worth one.
+    pub fn name(&self) -> Option<String> {
+        let mut parts = vec![];
+        if self.back_to_back {
+            parts.push("BACK-TO-BACK".to_string());
+        }
+        match self.spin {
+            Spin::None => (),
+            Spin::Mini => parts.push("T-SPIN MINI".to_string()),
+            Spin::Full => parts.push("T-SPIN".to_string()),
+        }
+        if self.difficult() {
+            let rows = (self.rows as usize).min(ROW_NAMES.len() - 1);
+            parts.push(ROW_NAMES[rows].to_string());
+        }
+        if self.combo > 0 {
+            parts.push(format!("{} COMBO", self.combo));
+        }
+        if self.perfect {
+            parts.push("PERFECT CLEAR".to_string());
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+/// Guideline scoring: cleared lines, the level they lead to, and points.
+#[derive(Clone, Debug)]
+pub struct Score {
+    pub points: u32,
+    pub lines: u32,
+    pub level: u32,
+    /// Clears in a row after the first one, or `None` once a lock clears nothing.
+    pub combo: Option<u32>,
+    /// Difficult clears in a row after the first one, or `None` once an
+    /// easy clear breaks the chain.
+    pub back_to_back: Option<u32>,
+    pub perfect_clears: u32,
+    /// Pieces locked so far.
+    pub pieces: u32,
+    /// Clears of 1, 2, 3, and 4 or more rows.
+    pub line_clears: [u32; 4],
+    /// T-spins and Minis that cleared lines.
+    pub t_spins: u32,
+}
+
+impl Score {
+    pub fn new(level: u32) -> Self {
+        Score {
+            points: 0,
+            lines: 0,
+            level,
+            combo: None,
+            back_to_back: None,
+            perfect_clears: 0,
+            pieces: 0,
+            line_clears: [0; 4],
+            t_spins: 0,
+        }
+    }
+
+    /// Awards a lock, going up a level every ten lines. `perfect` means
+    /// the lock left nothing on the board.
+    pub fn clear(&mut self, rows: u32, spin: Spin, perfect: bool) -> Clear {
+        let mut clear = Clear {
+            rows,
+            spin,
+            perfect: perfect && rows > 0,
+            ..Clear::NONE
+        };
+        self.pieces += 1;
+        if rows > 0 {
+            self.line_clears[(rows as usize).min(4) - 1] += 1;
+            if spin != Spin::None {
+                self.t_spins += 1;
+            }
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            self.combo = Some(combo);
+            clear.combo = combo;
+            if clear.difficult() {
+                clear.back_to_back = self.back_to_back.is_some();
+                self.back_to_back = Some(self.back_to_back.map_or(0, |chain| chain + 1));
+            } else {
+                self.back_to_back = None;
+            }
+        } else {
+            self.combo = None;
+        }
+        let mut points = clear.points();
+        if clear.back_to_back {
+            points = points * 3 / 2;
+        }
+        points += COMBO_POINTS * clear.combo;
+        if clear.perfect {
+            self.perfect_clears += 1;
+            points += if rows == 4 && clear.back_to_back {
+                BACK_TO_BACK_PERFECT_TETRIS
+            } else {
+                PERFECT_CLEAR_POINTS[(rows as usize).min(PERFECT_CLEAR_POINTS.len() - 1)]
+            };
+        }
+        self.points += points * self.level;
+        let before = self.lines / LINES_PER_LEVEL;
+        self.lines += rows;
+        self.level += self.lines / LINES_PER_LEVEL - before;
+        clear
+    }
+
+    pub fn soft_drop(&mut self, rows: u32) {
+        self.points += rows;
+    }
+
+    pub fn hard_drop(&mut self, rows: u32) {
+        self.points += 2 * rows;
+    }
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_clears() {
+        let mut score = Score::default();
+        score.clear(1, Spin::None, false);
+        score.clear(4, Spin::None, false);
+        assert_eq!((score.points, score.lines, score.level), (950, 5, 1));
+        score.clear(0, Spin::None, false);
+        score.clear(3, Spin::None, false);
+        assert_eq!((score.points, score.lines, score.level), (1450, 8, 1));
+        score.clear(2, Spin::None, false);
+        assert_eq!((score.points, score.lines, score.level), (1800, 10, 2));
+        assert_eq!(score.pieces, 5);
+        score.clear(4, Spin::None, false);
+        assert_eq!(score.points, 3600);
+        assert_eq!(score.line_clears, [1, 1, 1, 2]);
+        let mut score = Score::default();
+        let five = score.clear(5, Spin::None, false);
+        assert_eq!(five.name().unwrap(), "TETRIS");
+        assert_eq!((score.points, score.line_clears), (800, [0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn t_spins() {
+        let mut score = Score::new(2);
+        let double = score.clear(2, Spin::Full, false);
+        assert_eq!(score.points, 2400);
+        let mini = score.clear(0, Spin::Mini, false);
+        assert_eq!(score.points, 2600);
+        assert_eq!(double.name().unwrap(), "T-SPIN DOUBLE");
+        assert_eq!(mini.name().unwrap(), "T-SPIN MINI");
+        assert_eq!(score.t_spins, 1);
+        assert_eq!(
+            score.clear(4, Spin::None, false).name().unwrap(),
+            "BACK-TO-BACK TETRIS"
+        );
+        assert_eq!(Score::default().clear(2, Spin::None, false).name(), None);
+    }
+
+    #[test]
+    fn combos_and_back_to_back() {
+        let mut score = Score::default();
+        score.clear(4, Spin::None, false);
+        score.clear(0, Spin::None, false);
+        assert_eq!((score.combo, score.back_to_back), (None, Some(0)));
+        let tetris = score.clear(4, Spin::None, false);
+        assert!(tetris.back_to_back);
+        assert_eq!(score.points, 2000);
+        let single = score.clear(1, Spin::Full, false);
+        assert_eq!(score.points, 3250);
+        assert_eq!(single.name().unwrap(), "BACK-TO-BACK T-SPIN SINGLE 1 COMBO");
+        score.clear(1, Spin::None, false);
+        assert_eq!((score.combo, score.back_to_back), (Some(2), None));
+        let tetris = score.clear(4, Spin::None, false);
+        assert!(!tetris.back_to_back);
+        assert_eq!(score.points, 5350);
+        assert_eq!(tetris.name().unwrap(), "TETRIS 3 COMBO");
+    }
+
+    #[test]
+    fn perfect_clears() {
+        let mut score = Score::default();
+        let clear = score.clear(2, Spin::None, true);
+        assert_eq!(score.points, 1500);
+        assert_eq!(clear.name().unwrap(), "PERFECT CLEAR");
+        score.clear(0, Spin::None, false);
+        score.clear(4, Spin::None, true);
+        score.clear(0, Spin::None, false);
+        let clear = score.clear(4, Spin::None, true);
+        assert_eq!(clear.name().unwrap(), "BACK-TO-BACK TETRIS PERFECT CLEAR");
+        assert_eq!(score.points, 1500 + 2800 + 1200 + 3200);
+        assert_eq!(score.perfect_clears, 3);
+    }
+
+    #[test]
+    fn drops() {
+        let mut score = Score::new(5);
+        score.soft_drop(3);
+        score.hard_drop(10);
+        assert_eq!(score.points, 23);
+    }
+}
@@ -1,112 +1,358 @@
-use rand::{prelude::SliceRandom, Rng};
 use std::fmt;
 use termion::color::{self, Color as TermionColor};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Color {
-    None,
     Red,
     Green,
     Blue,
     Magenta,
     Yellow,
     Cyan,
+    Orange,
+    Gray,
 }
 
 impl Color {
-    const ALL: [Color; 6] = [
-        Self::Red,
-        Self::Green,
-        Self::Blue,
-        Self::Magenta,
-        Self::Yellow,
-        Self::Cyan,
-    ];
-
-    pub fn is_none(&self) -> bool {
-        matches!(self, Self::None)
+    fn termion(&self) -> &'static dyn TermionColor {
+        match self {
+            Self::Red => &color::Red,
+            Self::Green => &color::Green,
+            Self::Blue => &color::Blue,
+            Self::Magenta => &color::Magenta,
+            Self::Yellow => &color::Yellow,
+            Self::Cyan => &color::Cyan,
+            // the basic 16 colors have no orange, take it from the 256 color palette
+            Self::Orange => &color::AnsiValue(208),
+            Self::Gray => &color::LightBlack,
+        }
     }
+}
 
-    pub fn is_some(&self) -> bool {
-        !matches!(self, Self::None)
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.termion().write_bg(f)
     }
 }
 
-impl fmt::Display for Color {
+/// Displays a `Color` as the text color rather than the background.
+pub struct Foreground(pub Color);
+
+impl fmt::Display for Foreground {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Red => color::Red.write_bg(f),
-            Self::Green => color::Green.write_bg(f),
-            Self::Blue => color::Blue.write_bg(f),
-            Self::Magenta => color::Magenta.write_bg(f),
-            Self::Yellow => color::Yellow.write_bg(f),
-            Self::Cyan => color::Cyan.write_bg(f),
-            Self::None => Ok(()),
-        }
+        self.0.termion().write_fg(f)
     }
 }
 
+/// Which wall kicks a piece tries when it turns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kicks {
+    /// The SRS table for J, L, S, T and Z.
+    Srs,
+    /// The SRS table for I.
+    I,
+    /// Only turns in place.
+    None,
+}
+
+/// A kind of piece with any number of cells: how it looks in every
+/// orientation and how it turns.
+///
+/// This is a struct rather than an enum of the seven tetrominos so that
+/// piece sets can be loaded at runtime. Kinds compare by value, which tells
+/// them apart since no two kinds of a set share a name, and `GARBAGE` has a
+/// color custom sets can't pick.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PieceKind {
+    pub name: &'static str,
+    pub color: Color,
+    /// Cells in each orientation, as (row, column) offsets from the top
+    /// left corner of the bounding box the piece turns in.
+    pub cells: [&'static [(isize, isize)]; 4],
+    pub kicks: Kicks,
+    /// Whether turning it into a tight spot counts as a T-spin.
+    pub spin: bool,
+}
+
+/// The tetrominos with SRS bounding boxes and guideline colors.
+impl PieceKind {
+    pub const I: &'static PieceKind = &PieceKind {
+        name: "I",
+        color: Color::Cyan,
+        cells: [
+            &[(1, 0), (1, 1), (1, 2), (1, 3)],
+            &[(0, 2), (1, 2), (2, 2), (3, 2)],
+            &[(2, 3), (2, 2), (2, 1), (2, 0)],
+            &[(3, 1), (2, 1), (1, 1), (0, 1)],
+        ],
+        kicks: Kicks::I,
+        spin: false,
+    };
+    pub const J: &'static PieceKind = &PieceKind {
+        name: "J",
+        color: Color::Blue,
+        cells: [
+            &[(0, 0), (1, 0), (1, 1), (1, 2)],
+            &[(0, 2), (0, 1), (1, 1), (2, 1)],
+            &[(2, 2), (1, 2), (1, 1), (1, 0)],
+            &[(2, 0), (2, 1), (1, 1), (0, 1)],
+        ],
+        kicks: Kicks::Srs,
+        spin: false,
+    };
+    pub const T: &'static PieceKind = &PieceKind {
+        name: "T",
+        color: Color::Magenta,
+        cells: [
+            &[(0, 1), (1, 0), (1, 1), (1, 2)],
+            &[(1, 2), (0, 1), (1, 1), (2, 1)],
+            &[(2, 1), (1, 2), (1, 1), (1, 0)],
+            &[(1, 0), (2, 1), (1, 1), (0, 1)],
+        ],
+        kicks: Kicks::Srs,
+        spin: true,
+    };
+    pub const L: &'static PieceKind = &PieceKind {
+        name: "L",
+        color: Color::Orange,
+        cells: [
+            &[(0, 2), (1, 0), (1, 1), (1, 2)],
+            &[(2, 2), (0, 1), (1, 1), (2, 1)],
+            &[(2, 0), (1, 2), (1, 1), (1, 0)],
+            &[(0, 0), (2, 1), (1, 1), (0, 1)],
+        ],
+        kicks: Kicks::Srs,
+        spin: false,
+    };
+    pub const S: &'static PieceKind = &PieceKind {
+        name: "S",
+        color: Color::Green,
+        cells: [
+            &[(0, 1), (0, 2), (1, 0), (1, 1)],
+            &[(1, 2), (2, 2), (0, 1), (1, 1)],
+            &[(2, 1), (2, 0), (1, 2), (1, 1)],
+            &[(1, 0), (0, 0), (2, 1), (1, 1)],
+        ],
+        kicks: Kicks::Srs,
+        spin: false,
+    };
+    pub const Z: &'static PieceKind = &PieceKind {
+        name: "Z",
+        color: Color::Red,
+        cells: [
+            &[(0, 0), (0, 1), (1, 1), (1, 2)],
+            &[(0, 2), (1, 2), (1, 1), (2, 1)],
+            &[(2, 2), (2, 1), (1, 1), (1, 0)],
+            &[(2, 0), (1, 0), (1, 1), (0, 1)],
+        ],
+        kicks: Kicks::Srs,
+        spin: false,
+    };
+    // the O keeps a 2x2 box, so it never wobbles
+    pub const O: &'static PieceKind = &PieceKind {
+        name: "O",
+        color: Color::Yellow,
+        cells: [&[(0, 0), (0, 1), (1, 0), (1, 1)]; 4],
+        kicks: Kicks::None,
+        spin: false,
+    };
+    /// Fills the cells of garbage rows, never dealt as a piece.
+    pub const GARBAGE: &'static PieceKind = &PieceKind {
+        name: "garbage",
+        color: Color::Gray,
+        cells: [&[(0, 0)]; 4],
+        kicks: Kicks::None,
+        spin: false,
+    };
+
+    pub const ALL: [&'static PieceKind; 7] = [
+        Self::I,
+        Self::J,
+        Self::T,
+        Self::L,
+        Self::S,
+        Self::Z,
+        Self::O,
+    ];
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum Direction {
     Left,
     Right,
     Down,
 }
 
-const TETROMINOS: [[(usize, usize); 4]; 7] = [
-    [(0, 0), (1, 0), (2, 0), (3, 0)],
-    [(0, 0), (1, 0), (2, 0), (0, 1)],
-    [(0, 0), (1, 0), (2, 0), (1, 1)],
-    [(0, 0), (1, 0), (2, 0), (2, 1)],
-    [(0, 0), (1, 0), (2, 1), (1, 1)],
-    [(0, 1), (1, 0), (2, 0), (1, 1)],
-    [(0, 0), (1, 0), (0, 1), (1, 1)],
+/// SRS wall kicks as (row, column) offsets for each clockwise rotation,
+/// indexed by the orientation the piece turns from.
+const CW_KICKS: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (0, -1), (-1, -1), (2, 0), (2, -1)],
+    [(0, 0), (0, 1), (1, 1), (-2, 0), (-2, 1)],
+    [(0, 0), (0, 1), (-1, 1), (2, 0), (2, 1)],
+    [(0, 0), (0, -1), (1, -1), (-2, 0), (-2, -1)],
+];
+
+/// Same as `CW_KICKS`, but for counter-clockwise rotations.
+const CCW_KICKS: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (0, 1), (-1, 1), (2, 0), (2, 1)],
+    [(0, 0), (0, 1), (1, 1), (-2, 0), (-2, 1)],
+    [(0, 0), (0, -1), (-1, -1), (2, 0), (2, -1)],
+    [(0, 0), (0, -1), (1, -1), (-2, 0), (-2, -1)],
+];
+
+/// Same as `CW_KICKS`, but for the I piece.
+const I_CW_KICKS: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (0, -2), (0, 1), (1, -2), (-2, 1)],
+    [(0, 0), (0, -1), (0, 2), (-2, -1), (1, 2)],
+    [(0, 0), (0, 2), (0, -1), (-1, 2), (2, -1)],
+    [(0, 0), (0, 1), (0, -2), (2, 1), (-1, -2)],
+];
+
+/// Same as `CCW_KICKS`, but for the I piece.
+const I_CCW_KICKS: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (0, -1), (0, 2), (-2, -1), (1, 2)],
+    [(0, 0), (0, 2), (0, -1), (-1, 2), (2, -1)],
+    [(0, 0), (0, 1), (0, -2), (2, 1), (-1, -2)],
+    [(0, 0), (0, -2), (0, 1), (1, -2), (-2, 1)],
+];
+
+/// Kicks for 180 degree rotations, shared by all pieces. SRS itself has
+/// none, so these follow the widely used SRS+ table.
+const HALF_KICKS: [[(isize, isize); 6]; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (-1, -1), (0, 1), (0, -1)],
+    [(0, 0), (0, 1), (-2, 1), (-1, 1), (-2, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, -1), (1, 1), (0, -1), (0, 1)],
+    [(0, 0), (0, -1), (-2, -1), (-1, -1), (-2, 0), (-1, 0)],
 ];
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+/// SRS orientation states: 0, R, 2 and L.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Spawn,
+    Right,
+    Flip,
+    Left,
+}
+
+impl Orientation {
+    const ALL: [Orientation; 4] = [Self::Spawn, Self::Right, Self::Flip, Self::Left];
+
+    fn turned(self, rotation: Rotation) -> Self {
+        let quarters = match rotation {
+            Rotation::Clockwise => 1,
+            Rotation::Half => 2,
+            Rotation::CounterClockwise => 3,
+        };
+        Self::ALL[(self as usize + quarters) % 4]
+    }
+}
+
+/// A piece as its kind, how it is turned, and where its bounding box is.
+/// The origin is signed so that the box may hang off the grid while the
+/// cells themselves stay on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Tetromino {
-    pub cells: [(usize, usize); 4],
-    pub color: Color,
+    pub kind: &'static PieceKind,
+    pub orientation: Orientation,
+    /// Row and column of the top left corner of the bounding box.
+    pub origin: (isize, isize),
 }
 
 impl Tetromino {
-    pub fn new(index: usize, color: Color) -> Self {
+    /// A piece in its spawn orientation with the top left corner of its
+    /// bounding box on row 0, column 0. Keeping the box where SRS puts it
+    /// lines the flat I up with the bottom row of the other tetrominos.
+    pub fn new(kind: &'static PieceKind) -> Self {
         Tetromino {
-            cells: TETROMINOS[index],
-            color,
+            kind,
+            orientation: Orientation::Spawn,
+            origin: (0, 0),
         }
     }
 
-    pub fn new_with_rng(width: usize, rng: &mut impl Rng) -> Self {
-        let t = rng.gen_range(0, TETROMINOS.len());
-        let c = Color::ALL.choose(rng).unwrap();
-        let mut tetromino = Self::new(t, *c);
-        let center = rng.gen_range(0, width - 2);
-        for cell in tetromino.cells.iter_mut() {
-            cell.1 += center;
-        }
-        tetromino
+    pub fn color(&self) -> Color {
+        self.kind.color
+    }
+
+    /// Cells the piece covers on the grid.
+    pub fn cells(&self) -> Vec<(isize, isize)> {
+        let (y, x) = self.origin;
+        self.kind.cells[self.orientation as usize]
+            .iter()
+            .map(|(dy, dx)| (y + dy, x + dx))
+            .collect()
+    }
+
+    /// Centers a piece fresh out of `new` on a board `width` columns wide,
+    /// rounding to the left like the guideline does.
+    pub fn spawned(mut self, width: usize) -> Self {
+        let columns = self.kind.cells[0].iter().map(|cell| cell.1);
+        let (left, right) = (columns.clone().min().unwrap(), columns.max().unwrap());
+        self.origin.1 += (width as isize - (right - left + 1)).max(0) / 2 - left;
+        self
     }
 
     pub fn shift(&mut self, dir: Direction) {
-        for cell in self.cells.iter_mut() {
-            match dir {
-                Direction::Left => cell.1 -= 1,
-                Direction::Right => cell.1 += 1,
-                Direction::Down => cell.0 += 1,
+        match dir {
+            Direction::Left => self.origin.1 -= 1,
+            Direction::Right => self.origin.1 += 1,
+            Direction::Down => self.origin.0 += 1,
+        }
+    }
+
+    /// A copy of the piece moved one cell towards `dir`.
+    pub fn shifted(&self, dir: Direction) -> Self {
+        let mut shifted = self.clone();
+        shifted.shift(dir);
+        shifted
+    }
+
+    fn kicks(&self, rotation: Rotation) -> &'static [(isize, isize)] {
+        let from = self.orientation as usize;
+        match (rotation, self.kind.kicks) {
+            (_, Kicks::None) => &[(0, 0)],
+            (Rotation::Clockwise, Kicks::I) => &I_CW_KICKS[from],
+            (Rotation::Clockwise, _) => &CW_KICKS[from],
+            (Rotation::CounterClockwise, Kicks::I) => &I_CCW_KICKS[from],
+            (Rotation::CounterClockwise, _) => &CCW_KICKS[from],
+            (Rotation::Half, _) => &HALF_KICKS[from],
+        }
+    }
+
+    /// Rotates the piece, trying every SRS wall kick in order until `fits`
+    /// accepts the turned piece. Returns which of the kick tests worked.
+    pub fn rotate(&mut self, rotation: Rotation, fits: impl Fn(&Self) -> bool) -> Option<usize> {
+        for (test, &kick) in self.kicks(rotation).iter().enumerate() {
+            let rotated = self.rotated(rotation, kick);
+            if fits(&rotated) {
+                *self = rotated;
+                return Some(test);
             }
         }
+        None
+    }
+
+    /// The cell the piece turns about; only meaningful for pieces that
+    /// fit a 3x3 box, which all turn about a cell rather than a corner.
+    /// Piece sets only let such pieces spin.
+    pub fn center(&self) -> (isize, isize) {
+        (self.origin.0 + 1, self.origin.1 + 1)
     }
 
-    pub fn rotate(&mut self) -> Option<()> {
-        let (center_y, center_x) = self.cells[1];
-        for &i in &[0, 2, 3] {
-            let (y, x) = self.cells[i];
-            self.cells[i] = (
-                (center_y + x).checked_sub(center_x)?,
-                (center_x + center_y).checked_sub(y)?,
-            );
+    /// The piece turned within its bounding box and moved by `kick`.
+    fn rotated(&self, rotation: Rotation, kick: (isize, isize)) -> Self {
+        Tetromino {
+            kind: self.kind,
+            orientation: self.orientation.turned(rotation),
+            origin: (self.origin.0 + kick.0, self.origin.1 + kick.1),
         }
-        Some(())
     }
 }
 
@@ -114,20 +360,333 @@ impl Tetromino {
 mod tests {
     use super::*;
 
+    const ROTATIONS: [Rotation; 3] = [
+        Rotation::Clockwise,
+        Rotation::CounterClockwise,
+        Rotation::Half,
+    ];
+
+    fn centered(kind: &'static PieceKind) -> Tetromino {
+        let mut t = Tetromino::new(kind);
+        for _ in 0..4 {
+            t.shift(Direction::Down);
+            t.shift(Direction::Right);
+        }
+        t
+    }
+
+    #[test]
+    fn spawn_columns() {
+        let columns = |kind, width| {
+            let t = Tetromino::new(kind).spawned(width);
+            let mut columns = t.cells().iter().map(|cell| cell.1).collect::<Vec<_>>();
+            columns.sort_unstable();
+            columns.dedup();
+            columns
+        };
+        // I, T and O on the standard board
+        assert_eq!(columns(PieceKind::I, 10), [3, 4, 5, 6]);
+        assert_eq!(columns(PieceKind::T, 10), [3, 4, 5]);
+        assert_eq!(columns(PieceKind::O, 10), [4, 5]);
+        // rounded left when the piece can't be exactly centered
+        assert_eq!(columns(PieceKind::I, 9), [2, 3, 4, 5]);
+        assert_eq!(columns(PieceKind::T, 6), [1, 2, 3]);
+        assert_eq!(columns(PieceKind::O, 7), [2, 3]);
+    }
+
     #[test]
     fn shift() {
-        let mut t = Tetromino::new(0, Color::None);
+        let mut t = Tetromino::new(PieceKind::I);
+        assert_eq!(t.cells(), [(1, 0), (1, 1), (1, 2), (1, 3)]);
         t.shift(Direction::Down);
-        assert_eq!(t.cells, [(1, 0), (2, 0), (3, 0), (4, 0)]);
+        assert_eq!(t.cells(), [(2, 0), (2, 1), (2, 2), (2, 3)]);
         t.shift(Direction::Right);
-        assert_eq!(t.cells, [(1, 1), (2, 1), (3, 1), (4, 1)]);
+        assert_eq!(t.cells(), [(2, 1), (2, 2), (2, 3), (2, 4)]);
+    }
+
+    #[test]
+    fn origin_off_the_grid() {
+        let mut t = Tetromino::new(PieceKind::I);
+        assert_eq!(t.origin, (0, 0));
+        assert!(t.rotate(Rotation::CounterClockwise, |_| true).is_some());
+        t.shift(Direction::Left);
+        assert_eq!(t.origin, (0, -1));
+        assert_eq!(t.cells(), [(3, 0), (2, 0), (1, 0), (0, 0)]);
     }
 
     #[test]
     fn turn() {
-        let mut t = Tetromino::new(2, Color::None); // T
+        let mut t = Tetromino::new(PieceKind::T); // T
+        t.shift(Direction::Right);
+        assert!(t.rotate(Rotation::Clockwise, |_| true).is_some());
+        assert_eq!(t.cells(), [(1, 3), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(t.orientation, Orientation::Right);
+    }
+
+    #[test]
+    fn turn_back() {
+        let mut t = Tetromino::new(PieceKind::T); // T
+        t.shift(Direction::Right);
+        assert!(t.rotate(Rotation::CounterClockwise, |_| true).is_some());
+        assert_eq!(t.cells(), [(1, 1), (2, 2), (1, 2), (0, 2)]);
+        assert_eq!(t.orientation, Orientation::Left);
+        assert!(t.rotate(Rotation::Half, |_| true).is_some());
+        assert_eq!(t.cells(), [(1, 3), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(t.orientation, Orientation::Right);
+    }
+
+    #[test]
+    fn full_turn() {
+        for kind in PieceKind::ALL {
+            for (rotation, times) in ROTATIONS.iter().zip([4, 4, 2]) {
+                let mut t = centered(kind);
+                let start = t.cells();
+                for _ in 0..times {
+                    assert!(t.rotate(*rotation, |_| true).is_some());
+                }
+                assert_eq!(t.cells(), start);
+                assert_eq!(t.orientation, Orientation::Spawn);
+            }
+        }
+    }
+
+    #[test]
+    fn opposite_turns() {
+        for kind in PieceKind::ALL {
+            let mut cw = centered(kind);
+            let mut ccw = centered(kind);
+            let mut half = centered(kind);
+            assert!(cw.rotate(Rotation::Clockwise, |_| true).is_some());
+            assert!(cw.rotate(Rotation::Clockwise, |_| true).is_some());
+            for _ in 0..2 {
+                assert!(ccw.rotate(Rotation::CounterClockwise, |_| true).is_some());
+            }
+            assert!(half.rotate(Rotation::Half, |_| true).is_some());
+            assert_eq!(cw.cells(), ccw.cells());
+            assert_eq!(cw.cells(), half.cells());
+        }
+    }
+
+    #[test]
+    fn o_does_not_wobble() {
+        for rotation in ROTATIONS {
+            let mut t = Tetromino::new(PieceKind::O);
+            for _ in 0..4 {
+                assert!(t.rotate(rotation, |_| true).is_some());
+                let mut cells = t.cells();
+                cells.sort_unstable();
+                assert_eq!(cells, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+            }
+        }
+    }
+
+    #[test]
+    fn i_turns_about_its_center() {
+        let mut t = Tetromino::new(PieceKind::I);
+        t.shift(Direction::Down);
+        let expected = [
+            [(1, 2), (2, 2), (3, 2), (4, 2)],
+            [(3, 3), (3, 2), (3, 1), (3, 0)],
+            [(4, 1), (3, 1), (2, 1), (1, 1)],
+            [(2, 0), (2, 1), (2, 2), (2, 3)],
+        ];
+        for cells in &expected {
+            assert!(t.rotate(Rotation::Clockwise, |_| true).is_some());
+            assert_eq!(&t.cells(), cells);
+        }
+    }
+
+    #[test]
+    fn every_kick() {
+        for kind in PieceKind::ALL {
+            for rotation in ROTATIONS {
+                let mut t = centered(kind);
+                for from in Orientation::ALL {
+                    assert_eq!(t.orientation, from);
+                    let kicks = t.kicks(rotation);
+                    assert_eq!(kicks[0], (0, 0));
+                    for (test, &kick) in kicks.iter().enumerate() {
+                        let target = t.rotated(rotation, kick);
+                        let mut kicked = t.clone();
+                        // only the cells of the expected test are free
+                        assert_eq!(
+                            kicked.rotate(rotation, |piece| piece.cells() == target.cells()),
+                            Some(test)
+                        );
+                        assert_eq!(
+                            kicked.cells(),
+                            target.cells(),
+                            "{kind:?}, {rotation:?} from {from:?}, test {test}"
+                        );
+                        assert_eq!(kicked.orientation, from.turned(rotation));
+                        let base = t.rotated(rotation, (0, 0)).cells();
+                        let moved = base.iter().map(|(y, x)| (y + kick.0, x + kick.1));
+                        assert_eq!(target.cells(), moved.collect::<Vec<_>>());
+                    }
+                    assert!(t.clone().rotate(rotation, |_| false).is_none());
+                    assert!(t.rotate(Rotation::Clockwise, |_| true).is_some());
+                }
+            }
+        }
+    }
+
+    type KickReference = [(usize, Rotation, [(isize, isize); 5]); 8];
+
+    /// SRS kick offsets as listed in the guideline, in (x, y) with y
+    /// pointing up, for each orientation turned from and rotation.
+    const SRS_KICKS: KickReference = [
+        (
+            0,
+            Rotation::Clockwise,
+            [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        ),
+        (
+            1,
+            Rotation::CounterClockwise,
+            [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        ),
+        (
+            1,
+            Rotation::Clockwise,
+            [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        ),
+        (
+            2,
+            Rotation::CounterClockwise,
+            [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        ),
+        (
+            2,
+            Rotation::Clockwise,
+            [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        ),
+        (
+            3,
+            Rotation::CounterClockwise,
+            [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        ),
+        (
+            3,
+            Rotation::Clockwise,
+            [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        ),
+        (
+            0,
+            Rotation::CounterClockwise,
+            [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        ),
+    ];
+
+    /// Same as `SRS_KICKS`, but for the I piece.
+    const SRS_I_KICKS: KickReference = [
+        (
+            0,
+            Rotation::Clockwise,
+            [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        ),
+        (
+            1,
+            Rotation::CounterClockwise,
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        ),
+        (
+            1,
+            Rotation::Clockwise,
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        ),
+        (
+            2,
+            Rotation::CounterClockwise,
+            [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        ),
+        (
+            2,
+            Rotation::Clockwise,
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        ),
+        (
+            3,
+            Rotation::CounterClockwise,
+            [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        ),
+        (
+            3,
+            Rotation::Clockwise,
+            [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        ),
+        (
+            0,
+            Rotation::CounterClockwise,
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        ),
+    ];
+
+    #[test]
+    fn srs_reference_kicks() {
+        for (kind, reference) in [(PieceKind::T, SRS_KICKS), (PieceKind::I, SRS_I_KICKS)] {
+            for (from, rotation, offsets) in reference {
+                let mut t = centered(kind);
+                for _ in 0..from {
+                    assert!(t.rotate(Rotation::Clockwise, |_| true).is_some());
+                }
+                let base = t.rotated(rotation, (0, 0)).cells();
+                for (test, (x, y)) in offsets.into_iter().enumerate() {
+                    // rows grow downwards, so y is flipped
+                    let target = base.iter().map(|(r, c)| (r - y, c + x)).collect::<Vec<_>>();
+                    let mut kicked = t.clone();
+                    assert_eq!(
+                        kicked.rotate(rotation, |piece| piece.cells() == target),
+                        Some(test),
+                        "{} {rotation:?} from {from}, test {test}",
+                        kind.name
+                    );
+                    assert_eq!(kicked.cells(), target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kick_tables_mirror() {
+        // kicks of a rotation undo the kicks of the opposite one
+        for from in 0..4 {
+            let to = (from + 1) % 4;
+            for (cw, ccw) in [(&CW_KICKS, &CCW_KICKS), (&I_CW_KICKS, &I_CCW_KICKS)] {
+                for (a, b) in cw[from].iter().zip(ccw[to].iter()) {
+                    assert_eq!((a.0, a.1), (-b.0, -b.1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kick_off_the_wall() {
+        // vertical I against the left wall kicks one column to the right
+        let mut t = Tetromino::new(PieceKind::I);
+        t.shift(Direction::Down);
+        assert!(t.rotate(Rotation::CounterClockwise, |_| true).is_some());
+        t.shift(Direction::Left);
+        assert_eq!(t.cells(), [(4, 0), (3, 0), (2, 0), (1, 0)]);
+        let walls = |t: &Tetromino| t.cells().iter().all(|cell| (0..10).contains(&cell.1));
+        assert_eq!(t.rotate(Rotation::Clockwise, walls), Some(1));
+        assert_eq!(t.cells(), [(2, 0), (2, 1), (2, 2), (2, 3)]);
+        assert_eq!(t.orientation, Orientation::Spawn);
+    }
+
+    #[test]
+    fn kick_up_from_the_floor() {
+        // T lying on the floor of a 4-row board climbs one row to turn
+        let mut t = Tetromino::new(PieceKind::T);
+        t.shift(Direction::Down);
+        t.shift(Direction::Down);
         t.shift(Direction::Right);
-        t.rotate().unwrap();
-        assert_eq!(t.cells, [(1, 2), (1, 1), (1, 0), (2, 1)]);
+        let floor = |t: &Tetromino| t.cells().iter().all(|cell| cell.0 < 4);
+        assert!(t.rotate(Rotation::Clockwise, floor).is_some());
+        assert_eq!(t.cells(), [(2, 2), (1, 1), (2, 1), (3, 1)]);
+        assert!(t.rotate(Rotation::Clockwise, floor).is_some());
+        assert_eq!(t.cells(), [(3, 1), (2, 2), (2, 1), (2, 0)]);
+        assert!(t.rotate(Rotation::Half, floor).is_some());
+        assert_eq!(t.orientation, Orientation::Spawn);
+        assert!(t.cells().iter().all(|cell| cell.0 < 4));
     }
 }